@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::{Error, ErrorDetails};
+
+/// A handle to the ClickHouse instance backing inference storage. Cloning is cheap -- it's
+/// a thin wrapper around a pooled HTTP client, not a new connection.
+#[derive(Clone, Debug)]
+pub struct ClickHouseConnectionInfo {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl ClickHouseConnectionInfo {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            base_url,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Inserts `rows` into `table` using ClickHouse's `JSONEachRow` insert format.
+    pub async fn write<T: Serialize + Sync>(&self, rows: &[T], table: &str) -> Result<(), Error> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let mut body = Vec::new();
+        for row in rows {
+            serde_json::to_writer(&mut body, row).map_err(|e| {
+                Error::new(ErrorDetails::Serialization {
+                    message: format!("Failed to serialize row for `{table}`: {e}"),
+                })
+            })?;
+            body.push(b'\n');
+        }
+        self.client
+            .post(&self.base_url)
+            .query(&[(
+                "query",
+                format!("INSERT INTO {table} FORMAT JSONEachRow"),
+            )])
+            .body(body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!("Failed to write {} row(s) to `{table}`: {e}", rows.len()),
+                })
+            })?;
+        Ok(())
+    }
+
+    /// Runs `SELECT * FROM {table} WHERE {where_clause} FORMAT JSONEachRow` and deserializes
+    /// each returned line as `T`. Used by `poll_batch_inference_handler` to re-hydrate the
+    /// `BatchModelInference` rows `write_inference` wrote for a given `batch_id`.
+    pub async fn query_rows<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        where_clause: &str,
+    ) -> Result<Vec<T>, Error> {
+        let response = self
+            .client
+            .post(&self.base_url)
+            .query(&[(
+                "query",
+                format!("SELECT * FROM {table} WHERE {where_clause} FORMAT JSONEachRow"),
+            )])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!("Failed to query `{table}`: {e}"),
+                })
+            })?;
+        let body = response.text().await.map_err(|e| {
+            Error::new(ErrorDetails::Inference {
+                message: format!("Failed to read query response body from `{table}`: {e}"),
+            })
+        })?;
+        body.lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|e| {
+                    Error::new(ErrorDetails::Serialization {
+                        message: format!("Failed to deserialize row from `{table}`: {e}"),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the subset of `ids` that already have a finalized row in `chat_table` or
+    /// `json_table`, so a repeated poll of a completed batch doesn't re-finalize rows that
+    /// an earlier poll already wrote.
+    pub async fn query_existing_inference_ids(
+        &self,
+        chat_table: &str,
+        json_table: &str,
+        ids: &[Uuid],
+    ) -> Result<HashSet<Uuid>, Error> {
+        if ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let id_list = ids
+            .iter()
+            .map(|id| format!("'{id}'"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        #[derive(serde::Deserialize)]
+        struct IdRow {
+            id: Uuid,
+        }
+        let mut found = HashSet::new();
+        for table in [chat_table, json_table] {
+            let rows: Vec<IdRow> = self
+                .query_rows(table, &format!("id IN ({id_list})"))
+                .await?;
+            found.extend(rows.into_iter().map(|row| row.id));
+        }
+        Ok(found)
+    }
+}
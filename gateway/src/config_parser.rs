@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use crate::endpoints::batch_inference::{
+    BatchChatCompletionInferenceParams, BatchValidation, BatchValidationMode,
+};
+use crate::error::{Error, ErrorDetails};
+use crate::function::FunctionConfig;
+
+/// Parsed gateway configuration (`tensorzero.toml`), loaded once at startup and shared via
+/// `AppStateData`.
+#[derive(Debug)]
+pub struct Config {
+    pub functions: HashMap<String, FunctionConfig>,
+    pub tools: crate::tool::ToolConfig,
+    pub templates: TemplateConfig,
+    pub models: crate::model::ModelTable,
+    pub embedding_models: crate::model::EmbeddingModelTable,
+    /// Worker pool that pre-flight-validates batch requests (token budgets, shared
+    /// prefixes) off the request-handling task. `None` disables pre-flight validation
+    /// entirely: batches are still shape-checked (see `expand_batch_param`) but not
+    /// tokenized or checked against `max_input_length`/`max_total_tokens`.
+    pub batch_validation: Option<BatchValidation>,
+    /// How strictly batch parameter vectors (`temperature`, `max_tokens`, ...) are checked
+    /// for length against `num_inferences`. See `BatchValidationMode`.
+    #[allow(dead_code)]
+    pub batch_validation_mode: BatchValidationMode,
+    /// Named `[batch_inference_profiles.*]` entries a batch request can reference by name
+    /// (via `params.profile`) instead of repeating default values in every request.
+    pub batch_inference_params_profiles: HashMap<String, BatchChatCompletionInferenceParams>,
+    /// Hard cap, in tokens, on a single row's input, enforced by `BatchValidation`.
+    pub max_input_length: Option<usize>,
+    /// Hard cap, in tokens, on a single row's input plus its requested `max_tokens`.
+    pub max_total_tokens: Option<usize>,
+}
+
+impl Config {
+    pub fn get_function(&self, function_name: &str) -> Result<&FunctionConfig, Error> {
+        self.functions.get(function_name).ok_or_else(|| {
+            ErrorDetails::UnknownFunction {
+                name: function_name.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+/// The parsed `[templates]` section: the template environment used to render variant
+/// prompts. Reproduced as an opaque placeholder here since the batch inference path only
+/// threads `&Config.templates` through to `BatchInferenceConfig`; it never renders a
+/// template directly.
+#[derive(Debug, Default)]
+pub struct TemplateConfig {}
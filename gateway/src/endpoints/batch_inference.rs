@@ -1,14 +1,22 @@
 use axum::body::Body;
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::response::{IntoResponse, Response};
 use axum::{debug_handler, Json};
 use itertools::izip;
-use metrics::counter;
+use metrics::{counter, gauge, histogram};
+// `rdkafka` backs `run_kafka_batch_source` below; it belongs in this crate's `Cargo.toml`
+// dependencies (with the `cmake-build` or `dynamic-linking` feature, per upstream's install
+// notes) alongside the rest of this crate's manifest, which lives outside this snapshot.
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::{ClientConfig, Message};
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::iter::repeat;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tracing::instrument;
 use uuid::Uuid;
 
@@ -26,10 +34,79 @@ use crate::tool::{
 use crate::uuid_util::validate_episode_id;
 use crate::variant::{BatchInferenceConfig, Variant};
 
+/// The status of a submitted batch. There's no dedicated status row for a batch --
+/// `poll_batch_inference_handler` derives it each time from whether `ChatInference`/
+/// `JsonInference` rows already exist for the batch's inference ids
+/// (`query_finalized_inference_ids`) and, for rows still pending, what the provider
+/// reports. That existence check is also what makes polling idempotent: once a row has
+/// a finalized `ChatInference`/`JsonInference` row, it's never re-downloaded or
+/// re-written by a later poll.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    /// The batch has been written to ClickHouse, but the provider has not reported it as
+    /// in progress, completed or failed. Every freshly prepared batch starts here, and a
+    /// provider status that isn't recognized also maps here.
+    Pending,
+    /// The provider has accepted the batch and is processing it.
+    InProgress,
+    /// The provider finished the batch and we have written the finalized inferences.
+    Completed,
+    /// The provider reported the batch failed, expired or cancelled. An error while polling
+    /// is returned to the caller as an error and does not change the status.
+    Failed,
+}
+
 use super::inference::{
     ChatCompletionInferenceParams, InferenceClients, InferenceModels, InferenceParams,
 };
 
+/// Bucket boundaries (seconds) for `batch_inference_latency_seconds`. Pass these to the
+/// Prometheus exporter (e.g. `PrometheusBuilder::set_buckets_for_metric`) when it's built,
+/// so scrapes expose p50/p95 batch latency instead of only a cumulative total.
+pub const BATCH_LATENCY_BUCKETS_SECONDS: &[f64] =
+    &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+/// Bucket boundaries for `batch_inference_size` (number of inputs in a batch request).
+pub const BATCH_SIZE_BUCKETS: &[f64] = &[1.0, 10.0, 100.0, 1_000.0, 10_000.0, 100_000.0];
+
+/// Registers descriptions for the metrics this module emits. Call once during gateway
+/// startup (alongside wiring `BATCH_LATENCY_BUCKETS_SECONDS`/`BATCH_SIZE_BUCKETS` into the
+/// Prometheus exporter), before the first batch request is served.
+pub fn register_custom_metrics() {
+    metrics::describe_histogram!(
+        "batch_inference_latency_seconds",
+        metrics::Unit::Seconds,
+        "End-to-end latency of a batch inference request, from the start of the handler (input and token-budget validation included) through the ClickHouse write."
+    );
+    metrics::describe_histogram!(
+        "batch_inference_size",
+        metrics::Unit::Count,
+        "Number of inputs (num_inferences) in a batch inference request."
+    );
+    metrics::describe_counter!(
+        "batch_variant_failure_count",
+        "Number of times a sampled variant failed to complete a batch (or a per-inference row)."
+    );
+    metrics::describe_gauge!(
+        "batch_inference_active_model",
+        "Set to 1, labeled by batch_id, for each (function_name, model_name, model_provider_name, \
+         batch_id) combination with an in-flight batch; reset to 0 once that specific batch reaches \
+         a terminal state. Two concurrent batches on the same function/model/provider get distinct \
+         series instead of sharing one, so one finishing doesn't zero out the other. Aggregate across \
+         batch_id (e.g. `max by (function_name, model_name, model_provider_name)`) to see whether any \
+         batch is still serving that pair."
+    );
+    metrics::describe_counter!(
+        "batch_inference_kafka_malformed_rows_total",
+        "Number of Kafka batch source messages dropped for failing to parse as a KafkaBatchMessage."
+    );
+    metrics::describe_counter!(
+        "batch_inference_kafka_batch_failures_total",
+        "Number of Kafka-sourced batches dropped (not committed) after prepare_batch_inference_handler failed."
+    );
+}
+
 /// The expected payload is a JSON object with the following fields:
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -70,6 +147,13 @@ pub struct Params {
     pub output_schemas: Option<BatchOutputSchemas>,
     #[serde(default)]
     pub credentials: InferenceCredentials,
+    // Controls whether a single variant is sampled for the whole batch (the default,
+    // cheapest option) or whether each input gets its own independent variant sample.
+    // `per_inference` trades a bit of extra sampling work for resilience: one bad
+    // input only fails that input, instead of potentially disqualifying a variant
+    // (and retrying the whole batch under a different one) because of a single row.
+    #[serde(default)]
+    pub variant_sampling: VariantSamplingMode,
 }
 
 type BatchEpisodeIdInput = Vec<Option<Uuid>>;
@@ -77,6 +161,16 @@ type BatchEpisodeIds = Vec<Uuid>;
 type BatchTags = Vec<Option<HashMap<String, String>>>;
 type BatchOutputSchemas = Vec<Option<Value>>;
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VariantSamplingMode {
+    /// Sample one variant and use it for every input in the batch (previous, only behavior).
+    #[default]
+    PerBatch,
+    /// Sample a variant independently for each input, keyed off that input's own episode_id.
+    PerInference,
+}
+
 pub type InferenceCredentials = HashMap<String, SecretString>;
 
 /// A handler for the inference endpoint
@@ -97,6 +191,7 @@ pub async fn prepare_batch_inference_handler(
     }): AppState,
     StructuredJson(params): StructuredJson<Params>,
 ) -> Result<Response<Body>, Error> {
+    let start_time = Instant::now();
     // Get the function config or return an error if it doesn't exist
     let function = config.get_function(&params.function_name)?;
     let num_inferences = params.inputs.len();
@@ -106,6 +201,11 @@ pub async fn prepare_batch_inference_handler(
         }
         .into());
     }
+    histogram!(
+        "batch_inference_size",
+        "function_name" => params.function_name.to_string(),
+    )
+    .record(num_inferences as f64);
     let batch_dynamic_tool_params: Vec<DynamicToolParams> =
         BatchDynamicToolParamsWithSize(params.dynamic_tool_params, num_inferences).try_into()?;
 
@@ -125,19 +225,28 @@ pub async fn prepare_batch_inference_handler(
         .into());
     }
 
-    // Validate the input
-    params
-        .inputs
-        .iter()
-        .enumerate()
-        .try_for_each(|(i, input)| {
-            function.validate_input(input).map_err(|e| {
-                Error::new(ErrorDetails::BatchInputValidation {
-                    index: i,
-                    message: e.to_string(),
+    // Validate the input. Under `variant_sampling: per_inference` with `BatchValidationMode::
+    // Lenient`, a malformed input shouldn't fail the whole batch -- that row is instead
+    // dropped (as a `BatchInferenceFailure`) inside `prepare_batch_inference_per_inference`'s
+    // own per-row loop below. `per_batch` sampling has no per-row failure channel (every row
+    // shares one variant call and one write), so it always validates upfront regardless of
+    // `batch_validation_mode`.
+    if !(config.batch_validation_mode == BatchValidationMode::Lenient
+        && params.variant_sampling == VariantSamplingMode::PerInference)
+    {
+        params
+            .inputs
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, input)| {
+                function.validate_input(input).map_err(|e| {
+                    Error::new(ErrorDetails::BatchInputValidation {
+                        index: i,
+                        message: e.to_string(),
+                    })
                 })
-            })
-        })?;
+            })?;
+    }
 
     // If a variant is pinned, only that variant should be attempted
     if let Some(ref variant_name) = params.variant_name {
@@ -185,8 +294,77 @@ pub async fn prepare_batch_inference_handler(
         models: &config.models,
         embedding_models: &config.embedding_models,
     };
-    let inference_params: Vec<InferenceParams> =
-        BatchInferenceParamsWithSize(params.params, num_inferences).try_into()?;
+    let mut batch_params = params.params;
+    if let Some(profile_name) = &batch_params.profile {
+        let profile = config
+            .batch_inference_params_profiles
+            .get(profile_name)
+            .ok_or_else(|| {
+                Error::new(ErrorDetails::InvalidRequest {
+                    message: format!("Unknown batch inference params profile `{profile_name}`"),
+                })
+            })?;
+        batch_params.chat_completion = batch_params.chat_completion.with_profile_defaults(profile);
+    }
+    // Reject an oversized batch before any provider call is made. This runs on the
+    // `BatchValidation` worker pool rather than inline, since tokenizing every row of a
+    // large batch is CPU-bound work we don't want blocking the request-handling task.
+    // The per-row token accounting and shared-prefix grouping it returns is threaded into
+    // the rows written below (see `token_accounting_by_row`) rather than discarded --
+    // downstream cost/usage aggregation reads it off `BatchModelInference` per row, and
+    // can credit a shared prefix once per `prefix_group_id` instead of once per row.
+    let (token_accounting, prefix_group_ids) = if let Some(batch_validation) =
+        &config.batch_validation
+    {
+        let outcome = batch_validation
+            .validate(
+                BatchInferenceParamsWithSize(
+                    batch_params.clone(),
+                    num_inferences,
+                    config.batch_validation_mode,
+                ),
+                params.inputs.clone(),
+                config.max_input_length,
+                config.max_total_tokens,
+            )
+            .await?;
+        token_accounting_by_row(&outcome, num_inferences)
+    } else {
+        (vec![None; num_inferences], vec![None; num_inferences])
+    };
+
+    let inference_params: Vec<InferenceParams> = BatchInferenceParamsWithSize(
+        batch_params,
+        num_inferences,
+        config.batch_validation_mode,
+    )
+    .try_into()?;
+
+    let inference_configs = inference_config.inference_configs();
+
+    if params.variant_sampling == VariantSamplingMode::PerInference {
+        return prepare_batch_inference_per_inference(
+            &clickhouse_connection_info,
+            &params.function_name,
+            function,
+            candidate_variant_names,
+            params.inputs,
+            &episode_ids,
+            inference_params,
+            &inference_models,
+            &inference_configs,
+            &inference_clients,
+            // `inference_configs` above borrows `inference_config` for `'a`, so the
+            // original can't be moved out from under that borrow -- clone it, same as
+            // the per-batch path below does for `write_inference`.
+            inference_config.clone(),
+            params.tags,
+            &token_accounting,
+            &prefix_group_ids,
+            start_time,
+        )
+        .await;
+    }
 
     // Keep sampling variants until one succeeds
     // We already guarantee there is at least one inference
@@ -195,7 +373,6 @@ pub async fn prepare_batch_inference_handler(
         .ok_or_else(|| Error::new(ErrorDetails::Inference {
             message: "batch episode_ids unexpectedly empty. This should never happen. Please file a bug report: https://github.com/tensorzero/tensorzero/issues/new".to_string(),
         }))?;
-    let inference_configs = inference_config.inference_configs();
     while !candidate_variant_names.is_empty() {
         // We sample the same variant for the whole batch
         let (variant_name, variant) = sample_variant(
@@ -226,17 +403,44 @@ pub async fn prepare_batch_inference_handler(
                         function_name = params.function_name,
                         variant_name = variant_name,
                     );
+                counter!(
+                    "batch_variant_failure_count",
+                    "function_name" => params.function_name.to_string(),
+                    "variant_name" => variant_name.to_string(),
+                )
+                .increment(1);
                 variant_errors.insert(variant_name.to_string(), e);
                 continue;
             }
         };
 
+        // Set for the life of the in-flight batch: a scrape while a batch is still being
+        // submitted/finalized should see 1. It's reset to 0 on a separate completion path
+        // (`poll_batch_inference_handler`, once the provider reports the batch `Completed`
+        // or `Failed`) rather than synchronously here -- resetting it right after this
+        // (synchronous) write would make a scrape almost never observe the 1.
+        //
+        // Labeled by `batch_id` (not just function/model/provider) so a second, concurrent
+        // batch against the same function/model/provider gets its own series instead of
+        // sharing one -- otherwise the first batch completing would zero out the gauge while
+        // the second is still in flight.
+        gauge!(
+            "batch_inference_active_model",
+            "function_name" => params.function_name.to_string(),
+            "model_name" => result.model_name.to_string(),
+            "model_provider_name" => result.model_provider_name.to_string(),
+            "batch_id" => result.batch_id.to_string(),
+        )
+        .set(1.0);
+
         // Write to ClickHouse (don't spawn a thread for this because it's required)
         let write_metadata = BatchInferenceDatabaseInsertMetadata {
             function_name: params.function_name.as_str(),
-            variant_name,
+            variant_names: vec![variant_name; num_inferences],
             episode_ids: &episode_ids,
             tags: params.tags,
+            token_accounting,
+            prefix_group_ids,
         };
 
         let (batch_id, inference_ids) = write_inference(
@@ -250,10 +454,19 @@ pub async fn prepare_batch_inference_handler(
         )
         .await?;
 
+        histogram!(
+            "batch_inference_latency_seconds",
+            "function_name" => params.function_name.to_string(),
+            "variant_name" => variant_name.to_string(),
+        )
+        .record(start_time.elapsed().as_secs_f64());
+
         return Ok(Json(PrepareBatchInferenceOutput {
             batch_id,
             inference_ids,
             episode_ids,
+            status: BatchStatus::Pending,
+            failures: vec![],
         })
         .into_response());
     }
@@ -265,20 +478,576 @@ pub async fn prepare_batch_inference_handler(
     .into())
 }
 
+/// Handles `variant_sampling: per_inference`: each input samples its own variant
+/// (keyed on its own episode_id) and is submitted independently, so one bad input
+/// (or one variant erroring on one input) doesn't abandon the whole batch. Successful
+/// rows are written; failed indices are reported back to the caller instead of
+/// failing the request with `AllVariantsFailed`. Only when no row succeeds at all does
+/// the request fail with `AllVariantsFailed`, keyed by row index.
+#[allow(clippy::too_many_arguments)]
+async fn prepare_batch_inference_per_inference<'a>(
+    clickhouse_connection_info: &ClickHouseConnectionInfo,
+    function_name: &str,
+    function: &crate::function::FunctionConfig,
+    candidate_variant_names: Vec<&str>,
+    inputs: Vec<Input>,
+    episode_ids: &BatchEpisodeIds,
+    inference_params: Vec<InferenceParams>,
+    inference_models: &InferenceModels<'a>,
+    inference_configs: &Vec<crate::variant::InferenceConfig<'a>>,
+    inference_clients: &InferenceClients<'a>,
+    inference_config: BatchInferenceConfig<'a>,
+    tags: Option<BatchTags>,
+    token_accounting: &[Option<ValidInference>],
+    prefix_group_ids: &[Option<Uuid>],
+    start_time: Instant,
+) -> Result<Response<Body>, Error> {
+    // Generated upfront (rather than inside `write_inference_per_inference`) so the
+    // `batch_inference_active_model` gauge set below can be labeled with the same `batch_id`
+    // that `write_inference_per_inference` writes to ClickHouse and that gets returned to the
+    // caller -- `poll_batch_inference_handler` resets the gauge using that same id.
+    let batch_id = Uuid::now_v7();
+
+    // Indexed by the original input position `i`, not by how many rows have succeeded so
+    // far -- a failed row must not shift every later row's tags by one.
+    let tags = tags.unwrap_or_default();
+    let mut successes: Vec<PerInferenceSuccess<'a>> = Vec::with_capacity(inputs.len());
+    let mut failures: Vec<BatchInferenceFailure> = Vec::new();
+
+    for (i, input) in inputs.into_iter().enumerate() {
+        // Re-checked here (even though `prepare_batch_inference_handler` already validated
+        // every input upfront under `BatchValidationMode::Strict`) because under `Lenient`
+        // that upfront check is skipped entirely in favor of this per-row one -- an invalid
+        // input is dropped as a failure rather than failing the whole batch.
+        if let Err(e) = function.validate_input(&input) {
+            failures.push(BatchInferenceFailure {
+                index: i,
+                message: e.to_string(),
+            });
+            continue;
+        }
+
+        let mut candidates = candidate_variant_names.clone();
+        let sampled = sample_variant(
+            &mut candidates,
+            function.variants(),
+            function_name,
+            &episode_ids[i],
+        );
+        let (variant_name, variant) = match sampled {
+            Ok(sampled) => sampled,
+            Err(e) => {
+                failures.push(BatchInferenceFailure {
+                    index: i,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let result = variant
+            .start_batch_inference(
+                std::slice::from_ref(&input),
+                inference_models,
+                function,
+                inference_configs,
+                inference_clients,
+                vec![inference_params[i].clone()],
+            )
+            .await;
+
+        match result {
+            Ok(result) => {
+                gauge!(
+                    "batch_inference_active_model",
+                    "function_name" => function_name.to_string(),
+                    "model_name" => result.model_name.to_string(),
+                    "model_provider_name" => result.model_provider_name.to_string(),
+                    "batch_id" => batch_id.to_string(),
+                )
+                .set(1.0);
+                successes.push(PerInferenceSuccess {
+                    input,
+                    variant_name: variant_name.to_string(),
+                    episode_id: episode_ids[i],
+                    tags: tags.get(i).cloned().flatten(),
+                    original_index: i,
+                    result,
+                    token_accounting: token_accounting.get(i).copied().flatten(),
+                    prefix_group_id: prefix_group_ids.get(i).copied().flatten(),
+                });
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "functions.{function_name}.variants.{variant_name} failed during inference for index {i}: {e}",
+                );
+                counter!(
+                    "batch_variant_failure_count",
+                    "function_name" => function_name.to_string(),
+                    "variant_name" => variant_name.to_string(),
+                )
+                .increment(1);
+                failures.push(BatchInferenceFailure {
+                    index: i,
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
+
+    if successes.is_empty() {
+        return Err(ErrorDetails::AllVariantsFailed {
+            errors: failures
+                .into_iter()
+                .map(|f| {
+                    (
+                        f.index.to_string(),
+                        Error::new(ErrorDetails::Inference { message: f.message }),
+                    )
+                })
+                .collect(),
+        }
+        .into());
+    }
+
+    let (inference_ids, success_episode_ids) = write_inference_per_inference(
+        clickhouse_connection_info,
+        function_name,
+        batch_id,
+        successes,
+        inference_config,
+    )
+    .await?;
+
+    histogram!(
+        "batch_inference_latency_seconds",
+        "function_name" => function_name.to_string(),
+        "variant_name" => "per_inference",
+    )
+    .record(start_time.elapsed().as_secs_f64());
+
+    Ok(Json(PrepareBatchInferenceOutput {
+        batch_id,
+        inference_ids,
+        episode_ids: success_episode_ids,
+        status: BatchStatus::Pending,
+        failures,
+    })
+    .into_response())
+}
+
+struct PerInferenceSuccess<'a> {
+    input: Input,
+    variant_name: String,
+    episode_id: Uuid,
+    tags: Option<HashMap<String, String>>,
+    // Position of this row in the original `inputs` slice, so `write_inference_per_inference`
+    // can key `tool_configs` (and anything else aligned to the original batch) correctly
+    // even after earlier rows have been dropped as failures.
+    original_index: usize,
+    result: BatchModelInferenceWithMetadata<'a>,
+    token_accounting: Option<ValidInference>,
+    prefix_group_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchInferenceFailure {
+    pub index: usize,
+    pub message: String,
+}
+
+/// Writes one `BatchModelInference` row per successful `(input, variant)` pair produced
+/// by per-inference sampling. Unlike `write_inference`, each row may carry a different
+/// `variant_name`/`model_name`/`model_provider_name`, since every input was sampled and
+/// submitted independently.
+async fn write_inference_per_inference<'a>(
+    clickhouse_connection_info: &ClickHouseConnectionInfo,
+    function_name: &str,
+    batch_id: Uuid,
+    successes: Vec<PerInferenceSuccess<'a>>,
+    inference_config: BatchInferenceConfig<'a>,
+) -> Result<(Vec<Uuid>, Vec<Uuid>), Error> {
+    let batch_id_str = batch_id.to_string();
+    let mut chunk = Vec::with_capacity(BATCH_INSERT_CHUNK_SIZE);
+    let mut rows_written: usize = 0;
+    let mut inference_ids = Vec::with_capacity(successes.len());
+    let mut episode_ids = Vec::with_capacity(successes.len());
+    let tool_configs = inference_config.tool_configs;
+
+    for success in successes {
+        // Each per-inference call to `start_batch_inference` was made with a single-element
+        // slice, so every vector on `result` has exactly one entry.
+        let inference_id = *success.result.inference_ids.first().ok_or_else(|| {
+            Error::new(ErrorDetails::Inference {
+                message: "start_batch_inference returned no inference_ids for a single-row batch. This should never happen. Please file a bug report: https://github.com/tensorzero/tensorzero/issues/new".to_string(),
+            })
+        })?;
+        let input_messages = success.result.input_messages.first().ok_or_else(|| {
+            Error::new(ErrorDetails::Inference {
+                message: "start_batch_inference returned no input_messages for a single-row batch. This should never happen. Please file a bug report: https://github.com/tensorzero/tensorzero/issues/new".to_string(),
+            })
+        })?;
+        let system = success.result.systems.first().and_then(|s| s.as_ref());
+        let inference_params = success.result.inference_params.first().ok_or_else(|| {
+            Error::new(ErrorDetails::Inference {
+                message: "start_batch_inference returned no inference_params for a single-row batch. This should never happen. Please file a bug report: https://github.com/tensorzero/tensorzero/issues/new".to_string(),
+            })
+        })?;
+        let output_schema = success.result.output_schemas.first().and_then(|s| s.as_ref());
+
+        let input = serde_json::to_string(&success.input).map_err(|e| {
+            Error::new(ErrorDetails::Serialization {
+                message: e.to_string(),
+            })
+        })?;
+        let input_messages = serde_json::to_string(input_messages).map_err(|e| {
+            Error::new(ErrorDetails::Serialization {
+                message: e.to_string(),
+            })
+        })?;
+        let system = system
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| {
+                Error::new(ErrorDetails::Serialization {
+                    message: e.to_string(),
+                })
+            })?;
+        let output_schema = output_schema
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| {
+                Error::new(ErrorDetails::Serialization {
+                    message: e.to_string(),
+                })
+            })?;
+        // Keyed by the row's original position in the batch (like `episode_ids[i]` in
+        // `write_inference`), not by its position among successes: a row's tool config
+        // must not shift when an earlier row in the same batch failed.
+        let tool_params: Option<ToolCallConfigDatabaseInsert> = tool_configs
+            .get(success.original_index)
+            .cloned()
+            .flatten()
+            .map(|t| t.into());
+
+        chunk.push(BatchModelInferenceInsert {
+            id: inference_id.to_string(),
+            batch_id: &batch_id_str,
+            function_name,
+            variant_name: &success.variant_name,
+            episode_id: success.episode_id.to_string(),
+            input,
+            input_messages,
+            system,
+            tool_params,
+            inference_params,
+            output_schema,
+            model_name: success.result.model_name,
+            model_provider_name: success.result.model_provider_name,
+            tags: success.tags,
+            provider_batch_id: success.result.provider_batch_id.as_deref(),
+            token_accounting: success.token_accounting,
+            prefix_group_id: success.prefix_group_id.map(|id| id.to_string()),
+        });
+        inference_ids.push(inference_id);
+        episode_ids.push(success.episode_id);
+
+        if chunk.len() >= BATCH_INSERT_CHUNK_SIZE {
+            rows_written += flush_batch_model_inference_chunk(
+                clickhouse_connection_info,
+                &mut chunk,
+                rows_written,
+            )
+            .await?;
+        }
+    }
+    if !chunk.is_empty() {
+        rows_written +=
+            flush_batch_model_inference_chunk(clickhouse_connection_info, &mut chunk, rows_written)
+                .await?;
+    }
+    Ok((inference_ids, episode_ids))
+}
+
+/// A handler for polling a previously submitted batch.
+/// This queries the provider for the batch's status via the variant that originally
+/// submitted it, and, once the provider reports completion, writes the finalized
+/// `ChatInference`/`JsonInference` rows for each `inference_id` in the batch.
+///
+/// Calling this repeatedly is safe: rows that have already been finalized are
+/// skipped rather than re-written, so a `Completed` batch can be polled any
+/// number of times without duplicating inferences.
+///
+/// That safety isn't transactional, though: the already-finalized check
+/// (`query_finalized_inference_ids`) and the finalized write
+/// (`write_finalized_batch_inferences`) are two separate round trips with no lock between
+/// them, so two polls of the same completed batch racing each other can both see the same
+/// rows as pending and both write them, producing duplicate `ChatInference`/`JsonInference`
+/// rows. Fine for an occasional manually-triggered poll; a cron-based poller (or the Kafka
+/// source) should serialize polls per `batch_id` if that's a realistic way to call this.
+#[instrument(
+    name = "poll_batch_inference",
+    skip(config, http_client, clickhouse_connection_info, poll_params),
+    fields(batch_id = %batch_id)
+)]
+#[debug_handler(state = AppStateData)]
+pub async fn poll_batch_inference_handler(
+    State(AppStateData {
+        config,
+        http_client,
+        clickhouse_connection_info,
+    }): AppState,
+    Path(batch_id): Path<Uuid>,
+    StructuredJson(poll_params): StructuredJson<PollBatchInferenceParams>,
+) -> Result<Response<Body>, Error> {
+    let batch_rows = query_batch_model_inference_rows(&clickhouse_connection_info, batch_id).await?;
+    let Some(first_row) = batch_rows.first() else {
+        return Err(ErrorDetails::InvalidRequest {
+            message: format!("No batch found with id `{batch_id}`"),
+        }
+        .into());
+    };
+
+    let function = config.get_function(&first_row.function_name)?;
+
+    // Rows that have already been finalized (from a previous poll) are skipped;
+    // only the still-pending inference_ids are sent to the provider.
+    let already_finalized =
+        query_finalized_inference_ids(&clickhouse_connection_info, &batch_rows).await?;
+    let pending_rows: Vec<&BatchModelInferenceRow> = batch_rows
+        .iter()
+        .filter(|row| !already_finalized.contains(&row.id))
+        .collect();
+
+    if pending_rows.is_empty() {
+        return Ok(Json(PollBatchInferenceOutput {
+            batch_id,
+            status: BatchStatus::Completed,
+            inferences: batch_rows.iter().map(|row| row.id).collect(),
+        })
+        .into_response());
+    }
+
+    let inference_clients = InferenceClients {
+        http_client: &http_client,
+        clickhouse_connection_info: &clickhouse_connection_info,
+        credentials: &poll_params.credentials,
+    };
+
+    // Under `variant_sampling: per_inference` (see `prepare_batch_inference_per_inference`)
+    // rows sharing one `batch_id` may have been submitted independently, each under its own
+    // variant and provider batch id. Group by `(variant_name, provider_batch_id)` and poll
+    // each provider submission separately instead of assuming the whole row set is one
+    // homogeneous submission.
+    let mut groups: HashMap<(&str, Option<&str>), Vec<&BatchModelInferenceRow>> = HashMap::new();
+    for row in &pending_rows {
+        groups
+            .entry((row.variant_name.as_str(), row.provider_batch_id.as_deref()))
+            .or_default()
+            .push(row);
+    }
+
+    let mut statuses = Vec::with_capacity(groups.len());
+    let mut outputs = Vec::new();
+    // (function_name, model_name, model_provider_name) pairs whose batch reached a
+    // terminal state in this poll, so `batch_inference_active_model` can be reset now
+    // that they're no longer in flight. The gauge is labeled by this `batch_id` too (see
+    // the `set(1.0)` call sites in `prepare_batch_inference_handler` /
+    // `prepare_batch_inference_per_inference`), so resetting it here only affects this
+    // batch's own series -- a different, still-active batch on the same function/model/
+    // provider keeps its series at 1.
+    let mut finished_model_providers: Vec<(String, String, String)> = Vec::new();
+
+    for ((variant_name, _provider_batch_id), group_rows) in groups {
+        let variant = function.variants().get(variant_name).ok_or_else(|| {
+            Error::new(ErrorDetails::UnknownVariant {
+                name: variant_name.to_string(),
+            })
+        })?;
+        let group_result = variant
+            .poll_batch_inference(&group_rows, function, &inference_clients)
+            .await?;
+
+        if matches!(group_result.status, BatchStatus::Completed | BatchStatus::Failed) {
+            for row in &group_rows {
+                finished_model_providers.push((
+                    first_row.function_name.clone(),
+                    row.model_name.clone(),
+                    row.model_provider_name.clone(),
+                ));
+            }
+        }
+        if group_result.status == BatchStatus::Completed {
+            outputs.extend(group_result.outputs);
+        }
+        statuses.push(group_result.status);
+    }
+
+    let status = aggregate_batch_status(statuses);
+    if !outputs.is_empty() {
+        write_finalized_batch_inferences(&clickhouse_connection_info, outputs).await?;
+    }
+
+    finished_model_providers.sort();
+    finished_model_providers.dedup();
+    for (function_name, model_name, model_provider_name) in finished_model_providers {
+        gauge!(
+            "batch_inference_active_model",
+            "function_name" => function_name,
+            "model_name" => model_name,
+            "model_provider_name" => model_provider_name,
+            "batch_id" => batch_id.to_string(),
+        )
+        .set(0.0);
+    }
+
+    Ok(Json(PollBatchInferenceOutput {
+        batch_id,
+        status,
+        inferences: batch_rows.iter().map(|row| row.id).collect(),
+    })
+    .into_response())
+}
+
+/// Folds the per-group statuses from a (possibly split, under `per_inference` sampling)
+/// poll into one status for the whole batch: any group still failing/pending/in-progress
+/// keeps the batch from reading as `Completed`, and a single failed group is surfaced even
+/// if every other group succeeded, since the caller needs to know to investigate it.
+fn aggregate_batch_status(statuses: Vec<BatchStatus>) -> BatchStatus {
+    let mut any_failed = false;
+    let mut any_in_progress = false;
+    let mut all_completed = true;
+    for status in statuses {
+        match status {
+            BatchStatus::Completed => {}
+            BatchStatus::Failed => {
+                any_failed = true;
+                all_completed = false;
+            }
+            BatchStatus::InProgress => {
+                any_in_progress = true;
+                all_completed = false;
+            }
+            BatchStatus::Pending => all_completed = false,
+        }
+    }
+    if any_failed {
+        BatchStatus::Failed
+    } else if all_completed {
+        BatchStatus::Completed
+    } else if any_in_progress {
+        BatchStatus::InProgress
+    } else {
+        BatchStatus::Pending
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct PrepareBatchInferenceOutput {
     batch_id: Uuid,
     inference_ids: Vec<Uuid>,
     episode_ids: Vec<Uuid>,
+    status: BatchStatus,
+    // Indices (into the original `inputs`) that failed input validation (under
+    // `BatchValidationMode::Lenient`), variant sampling, or the variant call.
+    // Only ever non-empty under `variant_sampling: per_inference`; `per_batch`
+    // sampling still fails the whole request via `AllVariantsFailed`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    failures: Vec<BatchInferenceFailure>,
+}
+
+#[derive(Debug, Serialize)]
+struct PollBatchInferenceOutput {
+    batch_id: Uuid,
+    status: BatchStatus,
+    inferences: Vec<Uuid>,
+}
+
+/// Body of a poll request. Polling talks to the same model provider the batch was
+/// submitted to, so it needs the same kind of per-request credentials `Params.credentials`
+/// supplies to `/batch_inference`. If the map has no entry for the batch's model provider,
+/// the provider is called without a bearer token, same as with `Params.credentials` (see
+/// `ChatCompletionConfig::authed_get`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PollBatchInferenceParams {
+    #[serde(default)]
+    pub credentials: InferenceCredentials,
+}
+
+/// A row previously written to the `BatchModelInference` table, re-hydrated for polling.
+/// `pub(crate)` so `Variant::poll_batch_inference` implementations (see `crate::variant`)
+/// can read `provider_batch_id`/`episode_id`/etc. when asking the provider for a batch's
+/// status.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct BatchModelInferenceRow {
+    pub id: Uuid,
+    pub function_name: String,
+    pub variant_name: String,
+    pub episode_id: Uuid,
+    pub model_name: String,
+    pub model_provider_name: String,
+    pub provider_batch_id: Option<String>,
+}
+
+async fn query_batch_model_inference_rows(
+    clickhouse_connection_info: &ClickHouseConnectionInfo,
+    batch_id: Uuid,
+) -> Result<Vec<BatchModelInferenceRow>, Error> {
+    clickhouse_connection_info
+        .query_rows(
+            "BatchModelInference",
+            &format!("batch_id = '{batch_id}'"),
+        )
+        .await
+}
+
+async fn query_finalized_inference_ids(
+    clickhouse_connection_info: &ClickHouseConnectionInfo,
+    batch_rows: &[BatchModelInferenceRow],
+) -> Result<std::collections::HashSet<Uuid>, Error> {
+    let ids = batch_rows.iter().map(|row| row.id).collect::<Vec<_>>();
+    clickhouse_connection_info
+        .query_existing_inference_ids("ChatInference", "JsonInference", &ids)
+        .await
+}
+
+async fn write_finalized_batch_inferences(
+    clickhouse_connection_info: &ClickHouseConnectionInfo,
+    outputs: Vec<InferenceResponse>,
+) -> Result<(), Error> {
+    let (chat_rows, json_rows): (Vec<_>, Vec<_>) = outputs
+        .into_iter()
+        .partition(|output| matches!(output, InferenceResponse::Chat(_)));
+    if !chat_rows.is_empty() {
+        clickhouse_connection_info
+            .write(&chat_rows, "ChatInference")
+            .await?;
+    }
+    if !json_rows.is_empty() {
+        clickhouse_connection_info
+            .write(&json_rows, "JsonInference")
+            .await?;
+    }
+    Ok(())
 }
 
 #[derive(Debug)]
 struct BatchInferenceDatabaseInsertMetadata<'a> {
     pub function_name: &'a str,
-    pub variant_name: &'a str,
+    // One entry per row being written, aligned with `result.inference_ids`.
+    // In `per_batch` sampling this is the same variant name repeated; in
+    // `per_inference` sampling each row may name a different variant.
+    pub variant_names: Vec<&'a str>,
     pub episode_ids: &'a Vec<Uuid>,
     pub tags: Option<Vec<Option<HashMap<String, String>>>>,
     // pub tool_configs: &'a Vec<Option<ToolCallConfig>>,
+    // One entry per row, aligned with `episode_ids`/`variant_names`, from
+    // `BatchValidation::validate`'s `BatchValidationOutcome` (see
+    // `token_accounting_by_row`). All `None` when `config.batch_validation` isn't
+    // configured -- this is best-effort accounting, not required to write the batch.
+    pub token_accounting: Vec<Option<ValidInference>>,
+    pub prefix_group_ids: Vec<Option<Uuid>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -297,6 +1066,20 @@ struct BatchModelInferenceInsert<'a> {
     pub model_name: &'a str,
     pub model_provider_name: &'a str,
     pub tags: Option<HashMap<String, String>>,
+    // The opaque handle the provider's native batch API gave us for this job
+    // (e.g. an OpenAI batch id), so `poll_batch_inference_handler` knows what to poll.
+    // Always `Some` in practice: `ChatCompletionConfig::start_batch_inference` rejects
+    // submission for a provider/variant with no native batch API rather than writing a
+    // row with nothing to poll. Kept as `Option` since `BatchModelInferenceRow` (the type
+    // polling re-hydrates this column into) needs one for rows written before that check
+    // existed.
+    pub provider_batch_id: Option<&'a str>,
+    // This row's token accounting from pre-flight `BatchValidation`, if configured. See
+    // `ValidInference` -- `prefix_length` is this row's share of a `PrefixGroup`, keyed
+    // by `prefix_group_id` below, so downstream cost/usage aggregation can credit it once
+    // per group rather than once per row.
+    pub token_accounting: Option<ValidInference>,
+    pub prefix_group_id: Option<String>,
 }
 
 // Returns the batch ID and the inference IDs that were written to ClickHouse
@@ -307,8 +1090,12 @@ async fn write_inference<'a>(
     metadata: BatchInferenceDatabaseInsertMetadata<'a>,
     inference_config: BatchInferenceConfig<'a>,
 ) -> Result<(Uuid, Vec<Uuid>), Error> {
-    let mut rows = vec![];
     let batch_id = result.batch_id.to_string();
+    let mut inference_ids = Vec::with_capacity(result.inference_ids.len());
+    // Flushed to ClickHouse every `BATCH_INSERT_CHUNK_SIZE` rows instead of being
+    // accumulated for the whole batch, so memory stays bounded for very large batches.
+    let mut chunk: Vec<BatchModelInferenceInsert> = Vec::with_capacity(BATCH_INSERT_CHUNK_SIZE);
+    let mut rows_written: usize = 0;
 
     for (
         i,
@@ -320,6 +1107,9 @@ async fn write_inference<'a>(
         inference_params,
         output_schema,
         tags,
+        variant_name,
+        token_accounting,
+        prefix_group_id,
     ) in izip!(
         0..,
         result.inference_ids.iter(),
@@ -334,6 +1124,9 @@ async fn write_inference<'a>(
             .unwrap_or_default()
             .into_iter()
             .chain(repeat(None)),
+        metadata.variant_names.iter(),
+        metadata.token_accounting.iter(),
+        metadata.prefix_group_ids.iter(),
     ) {
         let input = serde_json::to_string(&input).map_err(|e| {
             Error::new(ErrorDetails::Serialization {
@@ -363,11 +1156,12 @@ async fn write_inference<'a>(
                     message: e.to_string(),
                 })
             })?;
-        rows.push(BatchModelInferenceInsert {
+        inference_ids.push(*inference_id);
+        chunk.push(BatchModelInferenceInsert {
             id: inference_id.to_string(),
             batch_id: &batch_id,
             function_name: metadata.function_name,
-            variant_name: metadata.variant_name,
+            variant_name,
             episode_id: metadata.episode_ids[i].to_string(),
             input,
             input_messages,
@@ -378,12 +1172,62 @@ async fn write_inference<'a>(
             model_name: result.model_name,
             model_provider_name: result.model_provider_name,
             tags,
+            // Populated by `Variant::start_batch_inference` when the provider
+            // accepted the batch natively; the same for every row in a batch.
+            provider_batch_id: result.provider_batch_id.as_deref(),
+            token_accounting: *token_accounting,
+            prefix_group_id: prefix_group_id.map(|id| id.to_string()),
         });
+
+        if chunk.len() >= BATCH_INSERT_CHUNK_SIZE {
+            rows_written += flush_batch_model_inference_chunk(
+                clickhouse_connection_info,
+                &mut chunk,
+                rows_written,
+            )
+            .await?;
+        }
+    }
+    if !chunk.is_empty() {
+        rows_written +=
+            flush_batch_model_inference_chunk(clickhouse_connection_info, &mut chunk, rows_written)
+                .await?;
     }
+    Ok((result.batch_id, inference_ids))
+}
+
+/// Number of rows buffered before issuing an insert, so a batch of tens of thousands of
+/// inputs doesn't hold every serialized row (and its input/messages/system/tool config) in
+/// memory at once, or require one giant insert.
+const BATCH_INSERT_CHUNK_SIZE: usize = 1_000;
+
+/// Writes one chunk of buffered rows, clearing the buffer, and returns how many rows were
+/// written. On failure, the error message reports how many rows were durably written by
+/// earlier chunks in this batch, so a mid-batch insert failure doesn't leave the caller
+/// guessing which rows made it to ClickHouse.
+///
+/// Generic over the row type so this chunking/flushing mechanism stays independent of
+/// `BatchModelInferenceInsert`'s exact field set (e.g. `provider_batch_id`, `variant_name`)
+/// -- it only needs rows it can serialize, not their shape.
+async fn flush_batch_model_inference_chunk<T: Serialize + Sync>(
+    clickhouse_connection_info: &ClickHouseConnectionInfo,
+    chunk: &mut Vec<T>,
+    rows_written_so_far: usize,
+) -> Result<usize, Error> {
+    let n = chunk.len();
     clickhouse_connection_info
-        .write(&rows, "BatchModelInference")
-        .await?;
-    Ok((result.batch_id, result.inference_ids))
+        .write(chunk, "BatchModelInference")
+        .await
+        .map_err(|e| {
+            Error::new(ErrorDetails::Inference {
+                message: format!(
+                    "Failed to write a chunk of {n} row(s) to ClickHouse after durably writing \
+                     {rows_written_so_far} row(s) of this batch: {e}"
+                ),
+            })
+        })?;
+    chunk.clear();
+    Ok(n)
 }
 
 /// InferenceResponse and InferenceResultChunk determine what gets serialized and sent to the client
@@ -395,8 +1239,16 @@ pub enum InferenceResponse {
     Json(JsonInferenceResponse),
 }
 
+// `inference_id` is renamed to `id` on the wire: these are written directly as
+// `ChatInference`/`JsonInference` rows (see `write_finalized_batch_inferences`), and
+// `query_existing_inference_ids` reads that same table back under the column name `id` --
+// the one every other table this feature writes (`BatchModelInference`) uses for its own
+// primary id. Keeping the Rust field named `inference_id` (it's populated from
+// `BatchModelInferenceRow::id`/`row.id` everywhere it's constructed) while renaming only
+// the serialized key keeps the two sides of that round trip agreeing on the column.
 #[derive(Clone, Debug, Serialize)]
 pub struct ChatInferenceResponse {
+    #[serde(rename = "id")]
     pub inference_id: Uuid,
     pub episode_id: Uuid,
     pub variant_name: String,
@@ -406,6 +1258,7 @@ pub struct ChatInferenceResponse {
 
 #[derive(Clone, Debug, Serialize)]
 pub struct JsonInferenceResponse {
+    #[serde(rename = "id")]
     pub inference_id: Uuid,
     pub episode_id: Uuid,
     pub variant_name: String,
@@ -413,6 +1266,16 @@ pub struct JsonInferenceResponse {
     pub usage: Usage,
 }
 
+/// The result of asking a `Variant` to poll a batch it previously submitted via
+/// `start_batch_inference`. Defined here (rather than in `variant.rs`) since
+/// `poll_batch_inference_handler` is what acts on it; `Variant::poll_batch_inference`
+/// implementations just construct one.
+pub struct PollBatchInferenceResult {
+    pub status: BatchStatus,
+    /// Finalized outputs, one per completed row. Only non-empty when `status` is `Completed`.
+    pub outputs: Vec<InferenceResponse>,
+}
+
 struct BatchEpisodeIdsWithSize(Option<BatchEpisodeIdInput>, usize);
 
 impl TryFrom<BatchEpisodeIdsWithSize> for BatchEpisodeIds {
@@ -458,34 +1321,116 @@ impl TryFrom<BatchEpisodeIdsWithSize> for BatchEpisodeIds {
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub struct BatchInferenceParams {
     pub chat_completion: BatchChatCompletionInferenceParams,
+    // References a `[batch_inference_profiles.*]` entry in config whose values act as
+    // defaults for any field `chat_completion` leaves unset. Lets a large, uniform batch
+    // define its temperature/max_tokens/etc. once in config instead of repeating a
+    // broadcast value in every request.
+    #[serde(default)]
+    pub profile: Option<String>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub struct BatchChatCompletionInferenceParams {
-    #[serde(default)]
+    // Accepts a bare scalar (broadcast to every inference), a length-1 vector (equivalent
+    // to the scalar form), or a length-`num_inferences` vector (one value per inference).
+    #[serde(default, deserialize_with = "deserialize_scalar_or_vec")]
     pub temperature: Option<Vec<Option<f32>>>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_scalar_or_vec")]
     pub max_tokens: Option<Vec<Option<u32>>>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_scalar_or_vec")]
     pub seed: Option<Vec<Option<u32>>>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_scalar_or_vec")]
     pub top_p: Option<Vec<Option<f32>>>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_scalar_or_vec")]
     pub presence_penalty: Option<Vec<Option<f32>>>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_scalar_or_vec")]
     pub frequency_penalty: Option<Vec<Option<f32>>>,
 }
 
-struct BatchInferenceParamsWithSize(BatchInferenceParams, usize);
+/// Deserializes a batch parameter field that may be given as a bare scalar (broadcast to
+/// every inference, equivalent to a length-1 vector), `null`/absent, or an explicit vector
+/// with one entry per inference. `expand_batch_param` is what actually performs the
+/// broadcast against `num_inferences`; this only normalizes the scalar form into a
+/// length-1 vector so that logic doesn't need to special-case it.
+fn deserialize_scalar_or_vec<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<Vec<Option<T>>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ScalarOrVec<T> {
+        Scalar(T),
+        Vec(Vec<Option<T>>),
+    }
+
+    Ok(Option::<ScalarOrVec<T>>::deserialize(deserializer)?.map(
+        |scalar_or_vec| match scalar_or_vec {
+            ScalarOrVec::Scalar(value) => vec![Some(value)],
+            ScalarOrVec::Vec(values) => values,
+        },
+    ))
+}
+
+impl BatchChatCompletionInferenceParams {
+    /// Fills in any field left unset by the caller with the named profile's value.
+    /// Per-item vectors the caller did supply always take priority over the profile.
+    fn with_profile_defaults(self, profile: &BatchChatCompletionInferenceParams) -> Self {
+        BatchChatCompletionInferenceParams {
+            temperature: self.temperature.or_else(|| profile.temperature.clone()),
+            max_tokens: self.max_tokens.or_else(|| profile.max_tokens.clone()),
+            seed: self.seed.or_else(|| profile.seed.clone()),
+            top_p: self.top_p.or_else(|| profile.top_p.clone()),
+            presence_penalty: self
+                .presence_penalty
+                .or_else(|| profile.presence_penalty.clone()),
+            frequency_penalty: self
+                .frequency_penalty
+                .or_else(|| profile.frequency_penalty.clone()),
+        }
+    }
+}
+
+/// Controls how strictly batch parameter vectors (and, under `variant_sampling:
+/// per_inference`, individual inputs) are validated. Defaults to `Strict` so interactive
+/// callers get a hard error on a malformed batch; high-throughput callers that pre-validate
+/// upstream can opt into `Lenient` via config to skip the redundant rejection.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchValidationMode {
+    /// A parameter vector whose length is neither 1 nor `num_inferences` is a hard error,
+    /// and any input that fails `FunctionConfig::validate_input` fails the whole batch.
+    #[default]
+    Strict,
+    /// An over-length vector is truncated to `num_inferences`; an under-length one (and not
+    /// length 1, which still broadcasts) is zero-filled (padded with `None`) instead of
+    /// erroring. Under `variant_sampling: per_inference`, a row whose input fails
+    /// `FunctionConfig::validate_input` is dropped (reported in `failures`, same as a
+    /// variant-sampling failure) instead of failing the whole batch. This does not apply
+    /// under the default `per_batch` sampling, since every row there shares one variant
+    /// call and one write -- a single invalid input still fails the whole batch in that
+    /// mode regardless of `BatchValidationMode`.
+    Lenient,
+}
+
+struct BatchInferenceParamsWithSize(BatchInferenceParams, usize, BatchValidationMode);
 impl TryFrom<BatchInferenceParamsWithSize> for Vec<InferenceParams> {
     type Error = Error;
 
     fn try_from(
-        BatchInferenceParamsWithSize(params, num_inferences): BatchInferenceParamsWithSize,
+        BatchInferenceParamsWithSize(params, num_inferences, mode): BatchInferenceParamsWithSize,
     ) -> Result<Self, Self::Error> {
-        let BatchInferenceParams { chat_completion } = params;
+        // `profile` is resolved against config and merged into `chat_completion` by the
+        // caller before this conversion runs (see `prepare_batch_inference_handler`),
+        // since resolving a named profile requires access to `Config`.
+        let BatchInferenceParams {
+            chat_completion,
+            profile: _,
+        } = params;
         let chat_completion_params: Vec<ChatCompletionInferenceParams> =
-            BatchChatCompletionParamsWithSize(chat_completion, num_inferences).try_into()?;
+            BatchChatCompletionParamsWithSize(chat_completion, num_inferences, mode).try_into()?;
         Ok(chat_completion_params
             .into_iter()
             .map(|p| InferenceParams { chat_completion: p })
@@ -493,12 +1438,16 @@ impl TryFrom<BatchInferenceParamsWithSize> for Vec<InferenceParams> {
     }
 }
 
-struct BatchChatCompletionParamsWithSize(BatchChatCompletionInferenceParams, usize);
+struct BatchChatCompletionParamsWithSize(
+    BatchChatCompletionInferenceParams,
+    usize,
+    BatchValidationMode,
+);
 impl TryFrom<BatchChatCompletionParamsWithSize> for Vec<ChatCompletionInferenceParams> {
     type Error = Error;
 
     fn try_from(
-        BatchChatCompletionParamsWithSize(params, num_inferences): BatchChatCompletionParamsWithSize,
+        BatchChatCompletionParamsWithSize(params, num_inferences, mode): BatchChatCompletionParamsWithSize,
     ) -> Result<Self, Self::Error> {
         let BatchChatCompletionInferenceParams {
             temperature,
@@ -508,114 +1457,600 @@ impl TryFrom<BatchChatCompletionParamsWithSize> for Vec<ChatCompletionInferenceP
             presence_penalty,
             frequency_penalty,
         } = params;
-        // Verify all provided Vecs have the same length
-        if let Some(temperature) = &temperature {
-            if temperature.len() != num_inferences {
-                return Err(ErrorDetails::InvalidRequest {
-                    message: format!(
-                        "temperature vector length ({}) does not match number of inferences ({})",
-                        temperature.len(),
-                        num_inferences
-                    ),
-                }
-                .into());
-            }
+
+        // Each field is either absent, a length-1 vector (broadcast to every inference),
+        // or a length-`num_inferences` vector (one value per inference); anything else is
+        // handled per `mode` (hard error, or truncated/zero-filled to length).
+        let temperature = expand_batch_param(temperature, num_inferences, "temperature", mode)?;
+        let max_tokens = expand_batch_param(max_tokens, num_inferences, "max_tokens", mode)?;
+        let seed = expand_batch_param(seed, num_inferences, "seed", mode)?;
+        let top_p = expand_batch_param(top_p, num_inferences, "top_p", mode)?;
+        let presence_penalty =
+            expand_batch_param(presence_penalty, num_inferences, "presence_penalty", mode)?;
+        let frequency_penalty =
+            expand_batch_param(frequency_penalty, num_inferences, "frequency_penalty", mode)?;
+
+        let mut all_inference_params = Vec::with_capacity(num_inferences);
+        for i in 0..num_inferences {
+            all_inference_params.push(ChatCompletionInferenceParams {
+                temperature: temperature[i],
+                max_tokens: max_tokens[i],
+                seed: seed[i],
+                top_p: top_p[i],
+                presence_penalty: presence_penalty[i],
+                frequency_penalty: frequency_penalty[i],
+            });
         }
+        Ok(all_inference_params)
+    }
+}
 
-        if let Some(max_tokens) = &max_tokens {
-            if max_tokens.len() != num_inferences {
-                return Err(ErrorDetails::InvalidRequest {
-                    message: format!(
-                        "max_tokens vector length ({}) does not match number of inferences ({})",
-                        max_tokens.len(),
-                        num_inferences
-                    ),
-                }
-                .into());
+/// Expands a per-batch parameter vector to exactly `num_inferences` entries: a missing
+/// vector becomes all-`None`, a length-1 vector is broadcast to every inference, and a
+/// length-`num_inferences` vector is used as-is. Any other length is an error under
+/// `BatchValidationMode::Strict`; under `Lenient` it is truncated or padded with `None`.
+fn expand_batch_param<T: Clone>(
+    values: Option<Vec<Option<T>>>,
+    num_inferences: usize,
+    field_name: &str,
+    mode: BatchValidationMode,
+) -> Result<Vec<Option<T>>, Error> {
+    match values {
+        None => Ok(vec![None; num_inferences]),
+        Some(values) if values.len() == num_inferences => Ok(values),
+        Some(mut values) if values.len() == 1 => {
+            let value = values.pop().flatten();
+            Ok(vec![value; num_inferences])
+        }
+        Some(mut values) if mode == BatchValidationMode::Lenient => {
+            // Truncate an over-length vector, or zero-fill (pad with `None`) a short one,
+            // rather than rejecting the whole batch over one malformed parameter vector.
+            values.truncate(num_inferences);
+            values.resize(num_inferences, None);
+            Ok(values)
+        }
+        Some(values) => Err(ErrorDetails::InvalidRequest {
+            message: format!(
+                "{field_name} vector length ({}) does not match number of inferences ({}) (a length-1 vector to broadcast a single value is also accepted)",
+                values.len(),
+                num_inferences
+            ),
+        }
+        .into()),
+    }
+}
+
+/// The outcome of a successful pre-flight validation for one row of a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ValidInference {
+    /// Tokens in this row's input beyond its shared prefix, if any -- see `prefix_length`.
+    /// This row's full input length is `input_tokens + prefix_length`.
+    pub input_tokens: usize,
+    /// Tokens this row's input shares with every other row in the same `PrefixGroup`, or
+    /// 0 if it isn't grouped with any other row. A provider that supports prefix caching
+    /// only needs to process this once per group rather than once per row, so downstream
+    /// cost/usage aggregation should credit it once per `PrefixGroup`, not once per row.
+    pub prefix_length: usize,
+    /// This row's full token budget: its full input length (`input_tokens +
+    /// prefix_length`) plus its requested `max_tokens`.
+    pub total_tokens: usize,
+}
+
+/// A group of batch rows whose inputs share a common leading run of tokens (e.g.
+/// identical system prompts or few-shot examples), detected by grouping rows on their
+/// longest common token prefix. Exposed so downstream cost/usage aggregation can credit
+/// the shared portion once instead of once per row, and so a provider that supports
+/// prefix caching can be told about the reuse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixGroup {
+    /// Indices (into the batch) of the rows that share `prefix_length` leading tokens.
+    pub row_indices: Vec<usize>,
+    /// Number of leading tokens shared by every row in `row_indices`.
+    pub prefix_length: usize,
+}
+
+/// A validated batch: each row's token accounting, plus any detected shared-prefix
+/// groups across the batch.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BatchValidationOutcome {
+    pub rows: Vec<ValidInference>,
+    pub prefix_groups: Vec<PrefixGroup>,
+}
+
+/// Re-indexes a `BatchValidationOutcome` by original row position, for threading into
+/// `BatchModelInferenceInsert` alongside the other per-row metadata (tags, tool configs,
+/// etc.). Every `PrefixGroup` is assigned a fresh id shared by its member rows, so
+/// downstream cost/usage aggregation can `GROUP BY` it and credit the shared prefix once
+/// rather than once per row; rows that aren't part of any group get `None`.
+fn token_accounting_by_row(
+    outcome: &BatchValidationOutcome,
+    num_inferences: usize,
+) -> (Vec<Option<ValidInference>>, Vec<Option<Uuid>>) {
+    let mut token_accounting = vec![None; num_inferences];
+    for (index, row) in outcome.rows.iter().enumerate() {
+        if let Some(slot) = token_accounting.get_mut(index) {
+            *slot = Some(*row);
+        }
+    }
+
+    let mut prefix_group_ids = vec![None; num_inferences];
+    for group in &outcome.prefix_groups {
+        let group_id = Uuid::now_v7();
+        for &index in &group.row_indices {
+            if let Some(slot) = prefix_group_ids.get_mut(index) {
+                *slot = Some(group_id);
             }
         }
+    }
+
+    (token_accounting, prefix_group_ids)
+}
+
+/// A chunk of a batch's inputs sent to a `BatchValidation` worker to tokenize. `reply` is a
+/// oneshot channel (rather than `validate` awaiting the work directly) so the CPU-bound
+/// tokenization happens entirely on a worker, not the caller's task.
+struct TokenizeJob {
+    inputs: Vec<Input>,
+    reply: oneshot::Sender<Result<Vec<Vec<u32>>, Error>>,
+}
 
-        if let Some(seed) = &seed {
-            if seed.len() != num_inferences {
-                return Err(ErrorDetails::InvalidRequest {
-                    message: format!(
-                        "seed vector length ({}) does not match number of inferences ({})",
-                        seed.len(),
-                        num_inferences
-                    ),
+/// A pool of tokio tasks, each owning its own tokenizer, that tokenize batch inputs for
+/// pre-flight validation before any provider call is made. `validate` splits a single
+/// batch's inputs into up to `num_workers` chunks and dispatches one `TokenizeJob` per
+/// chunk, so tokenizing one large batch is itself spread across the pool rather than
+/// pinned to whichever single worker happens to dequeue it. Tokenization is CPU-bound, so
+/// each worker runs it via `spawn_blocking` rather than inline on its own async task,
+/// keeping the async runtime responsive even while several large batches validate at once.
+/// Cloning a `BatchValidation` is cheap: it's just a channel sender and a worker count.
+#[derive(Clone, Debug)]
+pub struct BatchValidation {
+    sender: mpsc::UnboundedSender<TokenizeJob>,
+    num_workers: usize,
+}
+
+impl BatchValidation {
+    /// Spawns `num_workers` tasks sharing one job queue (at least one, even if
+    /// `num_workers` is 0 -- `validate` trusts `self.num_workers` to size the worker pool
+    /// it's dispatching into, so the two must never drift apart). Each task builds its own
+    /// tokenizer via `new_tokenizer` up front, so tokenizer construction never happens on
+    /// the request-handling path.
+    pub fn spawn<T, F>(num_workers: usize, new_tokenizer: F) -> Self
+    where
+        T: crate::tokenizer::Tokenizer + Send + Sync + 'static,
+        F: Fn() -> T,
+    {
+        let num_workers = num_workers.max(1);
+        let (sender, receiver) = mpsc::unbounded_channel::<TokenizeJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..num_workers {
+            let receiver = Arc::clone(&receiver);
+            let tokenizer = Arc::new(new_tokenizer());
+            tokio::spawn(async move {
+                loop {
+                    let job = receiver.lock().await.recv().await;
+                    let Some(TokenizeJob { inputs, reply }) = job else {
+                        break;
+                    };
+                    let tokenizer = Arc::clone(&tokenizer);
+                    // Tokenizing is CPU-bound; run it on the blocking-thread pool instead
+                    // of inline so this worker's async task never stalls the runtime while
+                    // it encodes a large chunk.
+                    let result = tokio::task::spawn_blocking(move || {
+                        tokenize_inputs(tokenizer.as_ref(), &inputs)
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        Err(Error::new(ErrorDetails::Inference {
+                            message: format!("batch validation tokenization task panicked: {e}"),
+                        }))
+                    });
+                    // The caller may have dropped its receiver (e.g. the request was
+                    // cancelled); nothing left to do with the result in that case.
+                    let _ = reply.send(result);
                 }
-                .into());
-            }
+            });
+        }
+        Self { sender, num_workers }
+    }
+
+    /// Validates every row of a batch against `max_input_length` and `max_total_tokens`,
+    /// offloading tokenization to the worker pool instead of running it on the caller's
+    /// task. The batch's inputs are split into up to `num_workers` chunks and tokenized
+    /// concurrently, since shared-prefix detection and the per-row budget checks below are
+    /// cheap compared to tokenization and don't need to run on the pool themselves. Returns
+    /// the first `BatchInputValidation` failure encountered, carrying the offending row's
+    /// index, if any row exceeds its budget.
+    pub async fn validate(
+        &self,
+        params: BatchInferenceParamsWithSize,
+        inputs: Vec<Input>,
+        max_input_length: Option<usize>,
+        max_total_tokens: Option<usize>,
+    ) -> Result<BatchValidationOutcome, Error> {
+        if inputs.is_empty() {
+            return assemble_validation_outcome(Vec::new(), params, max_input_length, max_total_tokens);
         }
 
-        if let Some(top_p) = &top_p {
-            if top_p.len() != num_inferences {
-                return Err(ErrorDetails::InvalidRequest {
-                    message: format!(
-                        "top_p vector length ({}) does not match number of inferences ({})",
-                        top_p.len(),
-                        num_inferences
-                    ),
+        let num_chunks = self.num_workers.min(inputs.len());
+        let chunk_size = inputs.len().div_ceil(num_chunks);
+
+        let mut receivers = Vec::with_capacity(num_chunks);
+        for chunk in inputs.chunks(chunk_size) {
+            let (reply, receiver) = oneshot::channel();
+            self.sender
+                .send(TokenizeJob {
+                    inputs: chunk.to_vec(),
+                    reply,
+                })
+                .map_err(|_| {
+                    Error::new(ErrorDetails::Inference {
+                        message: "batch validation worker pool has shut down".to_string(),
+                    })
+                })?;
+            receivers.push(receiver);
+        }
+
+        let mut token_ids = Vec::with_capacity(inputs.len());
+        for receiver in receivers {
+            let chunk_token_ids = receiver.await.map_err(|_| {
+                Error::new(ErrorDetails::Inference {
+                    message: "batch validation worker dropped the request without replying"
+                        .to_string(),
+                })
+            })??;
+            token_ids.extend(chunk_token_ids);
+        }
+
+        assemble_validation_outcome(token_ids, params, max_input_length, max_total_tokens)
+    }
+}
+
+/// Tokenizes each input's serialized form. This is the CPU-bound half of
+/// `BatchValidation::validate`, split out so it can run on the blocking-thread pool (see
+/// `BatchValidation::spawn`) independently of the (cheap) prefix-detection and budget
+/// checks in `assemble_validation_outcome`.
+fn tokenize_inputs(
+    tokenizer: &impl crate::tokenizer::Tokenizer,
+    inputs: &[Input],
+) -> Result<Vec<Vec<u32>>, Error> {
+    inputs
+        .iter()
+        .map(|input| {
+            let input_text = serde_json::to_string(input).map_err(|e| {
+                Error::new(ErrorDetails::Serialization {
+                    message: format!("Failed to serialize batch input for validation: {e}"),
+                })
+            })?;
+            Ok(tokenizer.encode(&input_text))
+        })
+        .collect()
+}
+
+/// Given each row's already-tokenized input (see `tokenize_inputs`), detects rows that
+/// share a common prompt prefix with one another and checks each row against
+/// `max_input_length` and `max_total_tokens` (its full input length -- prefix included --
+/// plus its own `max_tokens`). Cheap relative to tokenization, so it runs once over the
+/// whole batch after `BatchValidation::validate` has gathered every chunk's token ids,
+/// rather than per-chunk on the worker pool (shared-prefix detection needs the full batch
+/// in view to find prefixes that span a chunk boundary).
+fn assemble_validation_outcome(
+    token_ids: Vec<Vec<u32>>,
+    params: BatchInferenceParamsWithSize,
+    max_input_length: Option<usize>,
+    max_total_tokens: Option<usize>,
+) -> Result<BatchValidationOutcome, Error> {
+    let inference_params: Vec<InferenceParams> = params.try_into()?;
+
+    let prefix_groups = detect_shared_prefixes(&token_ids);
+    let mut prefix_length_by_index = vec![0; token_ids.len()];
+    for group in &prefix_groups {
+        for &index in &group.row_indices {
+            prefix_length_by_index[index] = group.prefix_length;
+        }
+    }
+
+    let rows = token_ids
+        .iter()
+        .zip(inference_params.iter())
+        .enumerate()
+        .map(|(index, (tokens, row_params))| {
+            let full_input_tokens = tokens.len();
+            if let Some(max_input_length) = max_input_length {
+                if full_input_tokens > max_input_length {
+                    return Err(Error::new(ErrorDetails::BatchInputValidation {
+                        index,
+                        message: format!(
+                            "input length ({full_input_tokens} tokens) exceeds max_input_length ({max_input_length} tokens)"
+                        ),
+                    }));
                 }
-                .into());
             }
+            let total_tokens =
+                full_input_tokens + row_params.chat_completion.max_tokens.unwrap_or(0) as usize;
+            if let Some(max_total_tokens) = max_total_tokens {
+                if total_tokens > max_total_tokens {
+                    return Err(Error::new(ErrorDetails::BatchInputValidation {
+                        index,
+                        message: format!(
+                            "total token budget ({total_tokens} tokens) exceeds max_total_tokens ({max_total_tokens} tokens)"
+                        ),
+                    }));
+                }
+            }
+            let prefix_length = prefix_length_by_index[index];
+            Ok(ValidInference {
+                input_tokens: full_input_tokens - prefix_length,
+                prefix_length,
+                total_tokens,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(BatchValidationOutcome {
+        rows,
+        prefix_groups,
+    })
+}
+
+/// Groups batch rows by their longest common leading run of tokens. Rows are sorted
+/// lexicographically by token sequence, so the longest common prefix of a run of
+/// consecutive rows in sorted order is the minimum of each adjacent pair's common
+/// prefix length; a run with more than one row (and a non-empty shared prefix) becomes
+/// a `PrefixGroup`. Singleton runs (nothing to share) are omitted.
+fn detect_shared_prefixes(token_ids: &[Vec<u32>]) -> Vec<PrefixGroup> {
+    if token_ids.len() < 2 {
+        return Vec::new();
+    }
+
+    let mut order: Vec<usize> = (0..token_ids.len()).collect();
+    order.sort_by(|&a, &b| token_ids[a].cmp(&token_ids[b]));
+
+    let mut groups = Vec::new();
+    let mut run_indices = vec![order[0]];
+    let mut run_prefix_len: Option<usize> = None;
+
+    for pair in order.windows(2) {
+        let (prev, next) = (pair[0], pair[1]);
+        let lcp = common_prefix_len(&token_ids[prev], &token_ids[next]);
+        if lcp == 0 {
+            if run_indices.len() > 1 {
+                groups.push(PrefixGroup {
+                    row_indices: std::mem::take(&mut run_indices),
+                    prefix_length: run_prefix_len.unwrap_or(0),
+                });
+            }
+            run_indices.clear();
+            run_prefix_len = None;
+        } else {
+            run_prefix_len = Some(run_prefix_len.map_or(lcp, |l| l.min(lcp)));
         }
+        run_indices.push(next);
+    }
+    if run_indices.len() > 1 {
+        groups.push(PrefixGroup {
+            row_indices: run_indices,
+            prefix_length: run_prefix_len.unwrap_or(0),
+        });
+    }
+    groups
+}
+
+/// Number of leading elements `a` and `b` have in common.
+fn common_prefix_len(a: &[u32], b: &[u32]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
+
+/// Governs where a Kafka consumer starts reading when it has no saved offset for its
+/// consumer group (i.e. on its very first run against a topic).
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaAutoOffsetReset {
+    /// Start from the newest record on the topic.
+    #[default]
+    Latest,
+    /// Start from the oldest retained record on the topic.
+    Earliest,
+}
+
+/// Settings for consuming batch inference requests from a Kafka topic instead of the
+/// HTTP `/batch_inference` endpoint. Every message on the topic is submitted against the
+/// same `function_name`; rows are buffered into batches of `batch_size` before being
+/// handed to [`prepare_batch_inference_handler`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct KafkaBatchSourceConfig {
+    pub brokers: String,
+    pub topic: String,
+    pub group_id: String,
+    pub function_name: String,
+    #[serde(default)]
+    pub auto_offset_reset: KafkaAutoOffsetReset,
+    #[serde(default = "default_kafka_batch_size")]
+    pub batch_size: usize,
+    /// How long to wait for the next row before submitting whatever has been buffered so
+    /// far. Without this, a topic that never accumulates `batch_size` rows in one session
+    /// would buffer forever and never submit a batch.
+    #[serde(default = "default_kafka_max_linger_ms")]
+    pub max_linger_ms: u64,
+}
+
+fn default_kafka_batch_size() -> usize {
+    100
+}
+
+fn default_kafka_max_linger_ms() -> u64 {
+    5_000
+}
+
+/// One row of a batch as consumed from a Kafka message: its input, an optional
+/// caller-supplied episode ID, and any per-row inference parameter overrides.
+#[derive(Deserialize)]
+struct KafkaBatchMessage {
+    input: Input,
+    #[serde(default)]
+    episode_id: Option<Uuid>,
+    #[serde(default)]
+    params: ChatCompletionInferenceParams,
+}
+
+/// Transposes the per-row overrides of a buffered batch into the vector-of-values shape
+/// `BatchChatCompletionInferenceParams` expects (one vector per field, one entry per row).
+fn assemble_batch_params(rows: &[KafkaBatchMessage]) -> BatchInferenceParams {
+    BatchInferenceParams {
+        chat_completion: BatchChatCompletionInferenceParams {
+            temperature: Some(rows.iter().map(|r| r.params.temperature).collect()),
+            max_tokens: Some(rows.iter().map(|r| r.params.max_tokens).collect()),
+            seed: Some(rows.iter().map(|r| r.params.seed).collect()),
+            top_p: Some(rows.iter().map(|r| r.params.top_p).collect()),
+            presence_penalty: Some(rows.iter().map(|r| r.params.presence_penalty).collect()),
+            frequency_penalty: Some(rows.iter().map(|r| r.params.frequency_penalty).collect()),
+        },
+        profile: None,
+    }
+}
 
-        if let Some(presence_penalty) = &presence_penalty {
-            if presence_penalty.len() != num_inferences {
-                return Err(ErrorDetails::InvalidRequest {
-                    message: format!(
-                        "presence_penalty vector length ({}) does not match number of inferences ({})",
-                        presence_penalty.len(),
-                        num_inferences
-                    ),
+/// Consumes inference requests from `source_config.topic`, buffers them into batches of
+/// `source_config.batch_size`, and submits each batch through the same path as an HTTP
+/// `/batch_inference` request. Auto-commit is disabled (`enable.auto.commit = false`):
+/// a batch's offsets are committed only after [`prepare_batch_inference_handler`] has
+/// durably written it to ClickHouse, so a crash resumes from the last committed offset
+/// instead of replaying or silently dropping in-flight requests. A message that fails to
+/// parse doesn't abort the batch; it's dropped and reported as a `BatchInputValidation`
+/// error at its position. A batch is also submitted once `source_config.max_linger_ms`
+/// elapses since the last message, even if fewer than `batch_size` rows have accumulated,
+/// so a low-volume topic doesn't wait forever for a full batch.
+pub async fn run_kafka_batch_source(
+    source_config: KafkaBatchSourceConfig,
+    app_state: AppStateData,
+) -> Result<(), Error> {
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("bootstrap.servers", &source_config.brokers)
+        .set("group.id", &source_config.group_id)
+        .set(
+            "auto.offset.reset",
+            match source_config.auto_offset_reset {
+                KafkaAutoOffsetReset::Earliest => "earliest",
+                KafkaAutoOffsetReset::Latest => "latest",
+            },
+        )
+        .set("enable.auto.commit", "false")
+        .create()
+        .map_err(|e| {
+            Error::new(ErrorDetails::Inference {
+                message: format!("Failed to create Kafka consumer: {e}"),
+            })
+        })?;
+
+    consumer
+        .subscribe(&[source_config.topic.as_str()])
+        .map_err(|e| {
+            Error::new(ErrorDetails::Inference {
+                message: format!(
+                    "Failed to subscribe to Kafka topic `{}`: {e}",
+                    source_config.topic
+                ),
+            })
+        })?;
+
+    let max_linger = std::time::Duration::from_millis(source_config.max_linger_ms);
+
+    loop {
+        let mut rows = Vec::with_capacity(source_config.batch_size);
+        let mut last_message = None;
+
+        while rows.len() < source_config.batch_size {
+            let message = match tokio::time::timeout(max_linger, consumer.recv()).await {
+                Ok(Ok(message)) => message,
+                Ok(Err(e)) => {
+                    return Err(Error::new(ErrorDetails::Inference {
+                        message: format!(
+                            "Failed to read from Kafka topic `{}`: {e}",
+                            source_config.topic
+                        ),
+                    }));
+                }
+                // `max_linger` elapsed with fewer than `batch_size` rows buffered: submit
+                // what we have rather than waiting indefinitely for a low-volume topic to
+                // fill a whole batch.
+                Err(_elapsed) => break,
+            };
+            let index = rows.len();
+            match message
+                .payload()
+                .map(serde_json::from_slice::<KafkaBatchMessage>)
+            {
+                Some(Ok(parsed)) => rows.push(parsed),
+                Some(Err(e)) => {
+                    let error = Error::new(ErrorDetails::BatchInputValidation {
+                        index,
+                        message: format!("Failed to parse Kafka batch message: {e}"),
+                    });
+                    tracing::warn!("Dropping malformed Kafka batch row: {error}");
+                    counter!("batch_inference_kafka_malformed_rows_total").increment(1);
+                }
+                None => {
+                    let error = Error::new(ErrorDetails::BatchInputValidation {
+                        index,
+                        message: "Kafka message has no payload".to_string(),
+                    });
+                    tracing::warn!("Dropping malformed Kafka batch row: {error}");
+                    counter!("batch_inference_kafka_malformed_rows_total").increment(1);
                 }
-                .into());
             }
+            last_message = Some(message);
         }
 
-        if let Some(frequency_penalty) = &frequency_penalty {
-            if frequency_penalty.len() != num_inferences {
-                return Err(ErrorDetails::InvalidRequest {
-                    message: format!(
-                        "frequency_penalty vector length ({}) does not match number of inferences ({})",
-                        frequency_penalty.len(),
-                        num_inferences
-                    ),
-                }
-                .into());
+        if !rows.is_empty() {
+            let params = Params {
+                function_name: source_config.function_name.clone(),
+                episode_ids: Some(rows.iter().map(|r| r.episode_id).collect()),
+                inputs: rows.iter().map(|r| r.input.clone()).collect(),
+                params: assemble_batch_params(&rows),
+                variant_name: None,
+                tags: None,
+                dynamic_tool_params: BatchDynamicToolParams::default(),
+                output_schemas: None,
+                credentials: InferenceCredentials::default(),
+                variant_sampling: VariantSamplingMode::default(),
+            };
+
+            // Reuse the HTTP handler's logic directly rather than duplicating variant
+            // sampling and the ClickHouse write path for this ingestion source. A single
+            // bad batch (e.g. every sampled variant failing, or a row over
+            // `max_total_tokens`) must not take down this topic's consumer the way
+            // propagating the error with `?` would -- log and drop the batch
+            // instead, without committing its offset: if the
+            // process keeps running we just move on to what's next on the topic, and if
+            // it crashes before the next successful commit, the next consumer retries
+            // this batch (in case the failure was transient) instead of silently losing
+            // it.
+            if let Err(e) =
+                prepare_batch_inference_handler(State(app_state.clone()), StructuredJson(params))
+                    .await
+            {
+                tracing::error!(
+                    "Dropping a Kafka batch of {} row(s) on topic `{}`: prepare_batch_inference_handler failed: {e}",
+                    rows.len(),
+                    source_config.topic,
+                );
+                counter!("batch_inference_kafka_batch_failures_total").increment(1);
+                continue;
             }
         }
 
-        // Convert Option<Vec<Option<T>>> into Vec<Option<T>> by unwrapping or creating empty vec
-        let temperature = temperature.unwrap_or_default();
-        let max_tokens = max_tokens.unwrap_or_default();
-        let seed = seed.unwrap_or_default();
-        let top_p = top_p.unwrap_or_default();
-        let presence_penalty = presence_penalty.unwrap_or_default();
-        let frequency_penalty = frequency_penalty.unwrap_or_default();
-
-        // Create iterators that take ownership
-        let mut temperature_iter = temperature.into_iter();
-        let mut max_tokens_iter = max_tokens.into_iter();
-        let mut seed_iter = seed.into_iter();
-        let mut top_p_iter = top_p.into_iter();
-        let mut presence_penalty_iter = presence_penalty.into_iter();
-        let mut frequency_penalty_iter = frequency_penalty.into_iter();
-
-        // Build params using the iterators
-        let mut all_inference_params = Vec::with_capacity(num_inferences);
-        for _ in 0..num_inferences {
-            all_inference_params.push(ChatCompletionInferenceParams {
-                temperature: temperature_iter.next().unwrap_or(None),
-                max_tokens: max_tokens_iter.next().unwrap_or(None),
-                seed: seed_iter.next().unwrap_or(None),
-                top_p: top_p_iter.next().unwrap_or(None),
-                presence_penalty: presence_penalty_iter.next().unwrap_or(None),
-                frequency_penalty: frequency_penalty_iter.next().unwrap_or(None),
-            });
+        // Only commit once the batch above has been durably submitted: on a crash before
+        // this point, the next consumer (re)starts from the last committed offset and
+        // re-reads everything buffered above, rather than dropping it.
+        // Commits the consumer's position on every assigned partition, not just the
+        // partition of the last message, since a batch can span several partitions.
+        if last_message.is_some() {
+            consumer
+                .commit_consumer_state(CommitMode::Sync)
+                .map_err(|e| {
+                    Error::new(ErrorDetails::Inference {
+                        message: format!("Failed to commit Kafka offset: {e}"),
+                    })
+                })?;
         }
-        Ok(all_inference_params)
     }
 }
 
@@ -662,7 +2097,7 @@ mod tests {
     fn test_batch_inference_params_with_size() {
         // Try with default params
         let batch_inference_params_with_size =
-            BatchInferenceParamsWithSize(BatchInferenceParams::default(), 3);
+            BatchInferenceParamsWithSize(BatchInferenceParams::default(), 3, BatchValidationMode::Strict);
         let inference_params =
             Vec::<InferenceParams>::try_from(batch_inference_params_with_size).unwrap();
         assert_eq!(inference_params.len(), 3);
@@ -682,8 +2117,10 @@ mod tests {
                     presence_penalty: Some(vec![Some(0.5), Some(0.6), Some(0.7)]),
                     frequency_penalty: Some(vec![Some(0.5), Some(0.6), Some(0.7)]),
                 },
+                profile: None,
             },
             3,
+            BatchValidationMode::Strict,
         );
 
         let inference_params =
@@ -741,22 +2178,24 @@ mod tests {
         let batch_inference_params_with_size = BatchInferenceParamsWithSize(
             BatchInferenceParams {
                 chat_completion: BatchChatCompletionInferenceParams {
-                    temperature: Some(vec![Some(0.5), None]), // Too short
+                    temperature: Some(vec![Some(0.5), None]), // Too short (and not a length-1 broadcast)
                     max_tokens: Some(vec![None, None, Some(30), Some(40)]), // Too long
                     seed: Some(vec![]),                       // Empty array
                     top_p: None,
-                    presence_penalty: Some(vec![Some(0.5)]), // Too short
+                    presence_penalty: Some(vec![Some(0.5)]), // Length 1: broadcast, not an error
                     frequency_penalty: Some(vec![Some(0.5), Some(0.6), Some(0.7), Some(0.8)]), // Too long
                 },
+                profile: None,
             },
             3,
+            BatchValidationMode::Strict,
         );
 
         let err = Vec::<InferenceParams>::try_from(batch_inference_params_with_size).unwrap_err();
         match err.get_details() {
             ErrorDetails::InvalidRequest { message } => assert_eq!(
                 message,
-                "temperature vector length (2) does not match number of inferences (3)"
+                "temperature vector length (2) does not match number of inferences (3) (a length-1 vector to broadcast a single value is also accepted)"
             ),
             _ => panic!("Expected InvalidRequest error"),
         }
@@ -772,17 +2211,200 @@ mod tests {
                     presence_penalty: Some(vec![Some(0.5), Some(0.6), Some(0.7)]),
                     frequency_penalty: Some(vec![Some(0.5), Some(0.6), Some(0.7)]),
                 },
+                profile: None,
             },
             4, // Wrong size - arrays are length 3 but size is 4
+            BatchValidationMode::Strict,
         );
 
         let err = Vec::<InferenceParams>::try_from(batch_inference_params_with_size).unwrap_err();
         match err.get_details() {
             ErrorDetails::InvalidRequest { message } => assert_eq!(
                 message,
-                "max_tokens vector length (3) does not match number of inferences (4)"
+                "max_tokens vector length (3) does not match number of inferences (4) (a length-1 vector to broadcast a single value is also accepted)"
             ),
             _ => panic!("Expected InvalidRequest error"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_batch_inference_params_lenient_mode() {
+        // Over-length vector is truncated rather than rejected.
+        let batch_inference_params_with_size = BatchInferenceParamsWithSize(
+            BatchInferenceParams {
+                chat_completion: BatchChatCompletionInferenceParams {
+                    temperature: Some(vec![Some(0.1), Some(0.2), Some(0.3), Some(0.4)]),
+                    max_tokens: None,
+                    seed: None,
+                    top_p: None,
+                    presence_penalty: None,
+                    frequency_penalty: None,
+                },
+                profile: None,
+            },
+            3,
+            BatchValidationMode::Lenient,
+        );
+        let inference_params =
+            Vec::<InferenceParams>::try_from(batch_inference_params_with_size).unwrap();
+        assert_eq!(inference_params.len(), 3);
+        assert_eq!(inference_params[0].chat_completion.temperature, Some(0.1));
+        assert_eq!(inference_params[2].chat_completion.temperature, Some(0.3));
+
+        // Under-length vector (and not length 1) is zero-filled rather than rejected.
+        let batch_inference_params_with_size = BatchInferenceParamsWithSize(
+            BatchInferenceParams {
+                chat_completion: BatchChatCompletionInferenceParams {
+                    temperature: None,
+                    max_tokens: Some(vec![Some(10), Some(20)]),
+                    seed: None,
+                    top_p: None,
+                    presence_penalty: None,
+                    frequency_penalty: None,
+                },
+                profile: None,
+            },
+            3,
+            BatchValidationMode::Lenient,
+        );
+        let inference_params =
+            Vec::<InferenceParams>::try_from(batch_inference_params_with_size).unwrap();
+        assert_eq!(inference_params.len(), 3);
+        assert_eq!(inference_params[0].chat_completion.max_tokens, Some(10));
+        assert_eq!(inference_params[1].chat_completion.max_tokens, Some(20));
+        assert_eq!(inference_params[2].chat_completion.max_tokens, None);
+    }
+
+    #[test]
+    fn test_batch_chat_completion_params_scalar_broadcast() {
+        // A bare scalar is accepted in place of a vector and broadcast to every inference.
+        let params: BatchChatCompletionInferenceParams = serde_json::from_str(
+            r#"{"temperature": 0.5, "max_tokens": 100, "seed": [1, 2, 3]}"#,
+        )
+        .unwrap();
+        assert_eq!(params.temperature, Some(vec![Some(0.5)]));
+        assert_eq!(params.max_tokens, Some(vec![Some(100)]));
+        assert_eq!(params.seed, Some(vec![Some(1), Some(2), Some(3)]));
+
+        let inference_params = Vec::<ChatCompletionInferenceParams>::try_from(
+            BatchChatCompletionParamsWithSize(params, 3, BatchValidationMode::Strict),
+        )
+        .unwrap();
+        assert_eq!(inference_params.len(), 3);
+        for p in &inference_params {
+            assert_eq!(p.temperature, Some(0.5));
+            assert_eq!(p.max_tokens, Some(100));
+        }
+        assert_eq!(inference_params[0].seed, Some(1));
+        assert_eq!(inference_params[1].seed, Some(2));
+        assert_eq!(inference_params[2].seed, Some(3));
+    }
+
+    #[test]
+    fn test_batch_chat_completion_params_with_profile_defaults() {
+        // Fields the caller left unset (here, everything but `temperature`) are filled in
+        // from the named profile.
+        let profile = BatchChatCompletionInferenceParams {
+            temperature: Some(vec![Some(0.9)]),
+            max_tokens: Some(vec![Some(256)]),
+            seed: None,
+            top_p: Some(vec![Some(0.8)]),
+            presence_penalty: None,
+            frequency_penalty: None,
+        };
+        let request = BatchChatCompletionInferenceParams {
+            temperature: Some(vec![Some(0.2)]),
+            max_tokens: None,
+            seed: None,
+            top_p: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+        };
+        let merged = request.with_profile_defaults(&profile);
+        // The caller's own value always wins over the profile's.
+        assert_eq!(merged.temperature, Some(vec![Some(0.2)]));
+        // Anything the caller left unset is backfilled from the profile.
+        assert_eq!(merged.max_tokens, Some(vec![Some(256)]));
+        assert_eq!(merged.top_p, Some(vec![Some(0.8)]));
+        // A field unset in both the request and the profile stays unset.
+        assert_eq!(merged.seed, None);
+        assert_eq!(merged.presence_penalty, None);
+    }
+
+    #[test]
+    fn test_aggregate_batch_status() {
+        // All groups completed: the batch is completed.
+        assert_eq!(
+            aggregate_batch_status(vec![BatchStatus::Completed, BatchStatus::Completed]),
+            BatchStatus::Completed
+        );
+        // A single failed group fails the whole batch, even if every other group succeeded.
+        assert_eq!(
+            aggregate_batch_status(vec![BatchStatus::Completed, BatchStatus::Failed]),
+            BatchStatus::Failed
+        );
+        // Failure wins over in-progress too.
+        assert_eq!(
+            aggregate_batch_status(vec![
+                BatchStatus::Failed,
+                BatchStatus::InProgress,
+                BatchStatus::Completed
+            ]),
+            BatchStatus::Failed
+        );
+        // No failures, but not everything is done yet: in-progress.
+        assert_eq!(
+            aggregate_batch_status(vec![BatchStatus::Completed, BatchStatus::InProgress]),
+            BatchStatus::InProgress
+        );
+        // Nothing failed or in-progress, but not everything completed: pending.
+        assert_eq!(
+            aggregate_batch_status(vec![BatchStatus::Completed, BatchStatus::Pending]),
+            BatchStatus::Pending
+        );
+        // Empty input (defensive case: no groups to aggregate): vacuously completed.
+        assert_eq!(aggregate_batch_status(vec![]), BatchStatus::Completed);
+    }
+
+    #[test]
+    fn test_detect_shared_prefixes() {
+        // Rows 0 and 2 share a 3-token prefix; row 1 shares nothing with either.
+        let token_ids = vec![vec![1, 2, 3, 4], vec![9, 9, 9], vec![1, 2, 3, 5]];
+        let groups = detect_shared_prefixes(&token_ids);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].prefix_length, 3);
+        let mut row_indices = groups[0].row_indices.clone();
+        row_indices.sort_unstable();
+        assert_eq!(row_indices, vec![0, 2]);
+
+        // No shared prefixes at all: no groups.
+        let token_ids = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        assert_eq!(detect_shared_prefixes(&token_ids), vec![]);
+
+        // Fewer than 2 rows: nothing to group.
+        assert_eq!(detect_shared_prefixes(&[vec![1, 2, 3]]), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_batch_validation_spawn_zero_workers_still_processes_jobs() {
+        // `num_workers: 0` must not mean "accept jobs but never run a worker to drain them" --
+        // that's the deadlock this test guards against (see the `spawn` doc comment).
+        let batch_validation = BatchValidation::spawn(0, || crate::tokenizer::WhitespaceTokenizer);
+        assert_eq!(batch_validation.num_workers, 1);
+
+        let (reply, receiver) = oneshot::channel();
+        batch_validation
+            .sender
+            .send(TokenizeJob {
+                inputs: Vec::new(),
+                reply,
+            })
+            .unwrap();
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), receiver)
+            .await
+            .expect("spawn(0, ..) left no worker to drain the job queue")
+            .unwrap();
+        assert_eq!(result.unwrap(), Vec::<Vec<u32>>::new());
+    }
+}
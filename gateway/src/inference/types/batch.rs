@@ -0,0 +1,31 @@
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::endpoints::inference::InferenceParams;
+
+/// What `Variant::start_batch_inference` hands back to
+/// `crate::endpoints::batch_inference::write_inference` (or
+/// `write_inference_per_inference`): everything needed to persist one `BatchModelInference`
+/// row per input, plus the provider handle (if any) needed to poll the batch later.
+///
+/// One entry per input in every `Vec` field below, aligned by index with the `inputs` slice
+/// the variant was called with.
+#[derive(Debug, Clone)]
+pub struct BatchModelInferenceWithMetadata<'a> {
+    pub batch_id: Uuid,
+    pub inference_ids: Vec<Uuid>,
+    /// The rendered messages sent to the model for each row.
+    pub input_messages: Vec<Value>,
+    /// The rendered system message for each row, if the function/variant has one.
+    pub systems: Vec<Option<Value>>,
+    pub inference_params: Vec<InferenceParams>,
+    /// The output schema override in effect for each row, if any.
+    pub output_schemas: Vec<Option<Value>>,
+    pub model_name: &'a str,
+    pub model_provider_name: &'a str,
+    /// The opaque handle the provider's native batch API returned for this submission
+    /// (e.g. an OpenAI batch id), the same for every row since a whole batch is submitted
+    /// as a single provider-side job. `None` for a variant/provider that doesn't support
+    /// native batch submission.
+    pub provider_batch_id: Option<String>,
+}
@@ -0,0 +1,77 @@
+//! Gateway binary entry point. Only the startup wiring relevant to the batch inference
+//! endpoints lives here; the rest of the gateway's startup sequence (loading config,
+//! building the router, etc.) lives outside this snapshot.
+
+use metrics_exporter_prometheus::PrometheusBuilder;
+
+use crate::endpoints::batch_inference::{
+    register_custom_metrics, run_kafka_batch_source, BatchValidation, KafkaBatchSourceConfig,
+    BATCH_LATENCY_BUCKETS_SECONDS, BATCH_SIZE_BUCKETS,
+};
+use crate::gateway_util::AppStateData;
+use crate::tokenizer::WhitespaceTokenizer;
+
+/// Delay before respawning a `run_kafka_batch_source` task that exited with an error, so a
+/// persistently broken broker/topic doesn't spin the task in a tight restart loop.
+const KAFKA_BATCH_SOURCE_RESTART_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Spawns one `run_kafka_batch_source` task per configured source, so each topic is
+/// consumed independently and one source's Kafka outage doesn't stall another's.
+/// `run_kafka_batch_source` only returns `Err` on a broker/connection-level failure (a bad
+/// individual batch is caught, logged and dropped without returning); that's still recoverable
+/// on most Kafka outages, so the task is respawned after a short delay rather than leaving
+/// the topic permanently unconsumed for the life of the process.
+fn install_kafka_batch_sources(sources: Vec<KafkaBatchSourceConfig>, app_state: AppStateData) {
+    for source_config in sources {
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            let topic = source_config.topic.clone();
+            loop {
+                if let Err(e) = run_kafka_batch_source(source_config.clone(), app_state.clone()).await {
+                    tracing::error!(
+                        "Kafka batch source for topic `{topic}` exited, restarting in {:?}: {e}",
+                        KAFKA_BATCH_SOURCE_RESTART_DELAY
+                    );
+                    tokio::time::sleep(KAFKA_BATCH_SOURCE_RESTART_DELAY).await;
+                    continue;
+                }
+                // `run_kafka_batch_source` only returns `Ok` if its loop is ever given a
+                // reason to stop on purpose; nothing to restart in that case.
+                break;
+            }
+        });
+    }
+}
+
+/// Number of tasks in the `BatchValidation` worker pool. Picked to give a single large batch
+/// real intra-batch parallelism without over-subscribing the blocking-thread pool that
+/// `BatchValidation` offloads tokenization onto; not meant to scale with core count the way
+/// e.g. a Tokio runtime's worker threads would.
+const BATCH_VALIDATION_WORKERS: usize = 4;
+
+/// Builds the `BatchValidation` worker pool for `Config.batch_validation`. Constructing it
+/// here (rather than lazily on first use) means tokenizer construction happens once at
+/// startup, off the request-handling path.
+fn install_batch_validation() -> BatchValidation {
+    BatchValidation::spawn(BATCH_VALIDATION_WORKERS, WhitespaceTokenizer::default)
+}
+
+fn install_prometheus_exporter() {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            metrics_exporter_prometheus::Matcher::Full("batch_inference_latency_seconds".to_string()),
+            BATCH_LATENCY_BUCKETS_SECONDS,
+        )
+        .expect("invalid batch_inference_latency_seconds buckets")
+        .set_buckets_for_metric(
+            metrics_exporter_prometheus::Matcher::Full("batch_inference_size".to_string()),
+            BATCH_SIZE_BUCKETS,
+        )
+        .expect("invalid batch_inference_size buckets")
+        .install()
+        .expect("failed to install Prometheus exporter");
+
+    // Registers descriptions (and thus units) for the metrics this module emits, so a
+    // scrape shows them before the first batch request is ever served.
+    register_custom_metrics();
+}
@@ -0,0 +1,54 @@
+//! Tokenization for pre-flight batch validation (see
+//! `crate::endpoints::batch_inference::BatchValidation`). Kept dependency-free: the
+//! validation worker pool only needs token *counts* and stable token *identity* (so two
+//! inputs that share a prefix produce the same leading token ids for
+//! `detect_shared_prefixes`), not a provider-accurate vocabulary.
+
+/// Something that can turn an input's serialized text into a sequence of token ids.
+/// `BatchValidation::spawn` builds one per worker task, so implementations must be
+/// `Send` and cheap to construct.
+pub trait Tokenizer: Send + Sync {
+    /// Encodes `text` into token ids. Must be deterministic for a given input: two inputs
+    /// that share a textual prefix are expected to share the same leading ids.
+    fn encode(&self, text: &str) -> Vec<u32>;
+}
+
+/// A whitespace-splitting tokenizer: each token id is a hash of one whitespace-delimited
+/// word. Not provider-accurate (real token boundaries rarely match whitespace), but
+/// deterministic and allocation-light, which is all `max_input_length`/`max_total_tokens`
+/// enforcement and shared-prefix detection need. Swap in a provider-specific tokenizer via
+/// `BatchValidation::spawn` when exact counts matter more than speed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn encode(&self, text: &str) -> Vec<u32> {
+        use std::hash::{Hash, Hasher};
+
+        text.split_whitespace()
+            .map(|word| {
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                word.hash(&mut hasher);
+                hasher.finish() as u32
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whitespace_tokenizer_is_deterministic_and_prefix_stable() {
+        let tokenizer = WhitespaceTokenizer;
+        assert_eq!(
+            tokenizer.encode("hello world"),
+            tokenizer.encode("hello world")
+        );
+        let a = tokenizer.encode("hello world foo");
+        let b = tokenizer.encode("hello world bar");
+        assert_eq!(a[..2], b[..2]);
+        assert_ne!(a[2], b[2]);
+    }
+}
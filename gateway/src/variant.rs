@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::endpoints::batch_inference::{
+    BatchModelInferenceRow, BatchStatus, ChatInferenceResponse, InferenceResponse,
+    JsonInferenceResponse, PollBatchInferenceResult,
+};
+use crate::endpoints::inference::{InferenceClients, InferenceModels, InferenceParams};
+use crate::error::{Error, ErrorDetails};
+use crate::function::FunctionConfig;
+use crate::inference::types::batch::BatchModelInferenceWithMetadata;
+use crate::inference::types::{ContentBlockOutput, Input, JsonInferenceOutput, Text, Usage};
+use crate::tool::ToolCallConfig;
+
+/// A function's variant: the thing `sample_variant` picks between and that actually talks
+/// to a model provider. `start_batch_inference`/`poll_batch_inference` are the batch-mode
+/// counterparts of (the otherwise out-of-scope-here) `infer`/`infer_stream`.
+#[async_trait]
+pub trait Variant: Send + Sync {
+    /// Submits `inputs` as a single batch job. Implementations that talk to a provider
+    /// with a native batch API (e.g. OpenAI's `/v1/batches`) should enqueue the job there
+    /// and return its handle as `BatchModelInferenceWithMetadata::provider_batch_id`, so
+    /// `poll_batch_inference` can later ask the provider for its status.
+    async fn start_batch_inference<'a>(
+        &'a self,
+        inputs: &[Input],
+        models: &InferenceModels<'a>,
+        function: &FunctionConfig,
+        inference_configs: &[InferenceConfig<'a>],
+        clients: &InferenceClients<'a>,
+        inference_params: Vec<InferenceParams>,
+    ) -> Result<BatchModelInferenceWithMetadata<'a>, Error>;
+
+    /// Asks the provider for the status of a previously submitted batch, and, if it has
+    /// finished, the finalized outputs for `pending_rows`. `pending_rows` are all rows of
+    /// one `(variant_name, provider_batch_id)` group -- callers must not mix rows from
+    /// different provider submissions into one call, since providers report status and
+    /// results per submission.
+    async fn poll_batch_inference(
+        &self,
+        pending_rows: &[&BatchModelInferenceRow],
+        function: &FunctionConfig,
+        clients: &InferenceClients<'_>,
+    ) -> Result<PollBatchInferenceResult, Error>;
+}
+
+/// Per-row configuration handed to a variant alongside its input: the tool config and
+/// output schema override in effect for that specific row of the batch.
+#[derive(Debug, Clone)]
+pub struct InferenceConfig<'a> {
+    pub templates: &'a crate::config_parser::TemplateConfig,
+    pub tool_config: Option<&'a ToolCallConfig>,
+    pub dynamic_output_schema: Option<&'a Value>,
+}
+
+/// The per-batch configuration shared by every row: the template environment, each row's
+/// tool config, and each row's output schema override. `inference_configs` fans this out
+/// into one `InferenceConfig` per row for the variant to consume.
+#[derive(Debug, Clone)]
+pub struct BatchInferenceConfig<'a> {
+    pub templates: &'a crate::config_parser::TemplateConfig,
+    pub tool_configs: Vec<Option<ToolCallConfig>>,
+    pub output_schemas: Option<Vec<Option<Value>>>,
+}
+
+impl<'a> BatchInferenceConfig<'a> {
+    pub fn new(
+        templates: &'a crate::config_parser::TemplateConfig,
+        tool_configs: Vec<Option<ToolCallConfig>>,
+        output_schemas: Option<Vec<Option<Value>>>,
+    ) -> Self {
+        Self {
+            templates,
+            tool_configs,
+            output_schemas,
+        }
+    }
+
+    /// Builds one `InferenceConfig` per row, pairing each row's tool config with its output
+    /// schema override. A caller-supplied `output_schemas` vector shorter than the batch
+    /// just leaves the remaining rows with `None` (no override), same as an absent vector.
+    pub fn inference_configs(&'a self) -> Vec<InferenceConfig<'a>> {
+        let output_schemas = self.output_schemas.as_deref().unwrap_or(&[]);
+        self.tool_configs
+            .iter()
+            .enumerate()
+            .map(|(i, tool_config)| InferenceConfig {
+                templates: self.templates,
+                tool_config: tool_config.as_ref(),
+                dynamic_output_schema: output_schemas.get(i).and_then(|schema| schema.as_ref()),
+            })
+            .collect()
+    }
+}
+
+/// The chat-completion variant. `start_batch_inference`/`poll_batch_inference` talk to the
+/// model provider's native, OpenAI-`/v1/batches`-shaped batch API: submission uploads the
+/// rendered requests and stores the provider's batch id; polling asks the provider for that
+/// batch's status and, once it reports completion, downloads and parses the outputs.
+#[derive(Debug, Clone)]
+pub struct ChatCompletionConfig {
+    pub model_name: String,
+    pub model_provider_name: String,
+    /// Base URL of the provider's native batch API (e.g. `https://api.openai.com/v1`).
+    /// `None` means this model/provider doesn't support native batch submission;
+    /// `start_batch_inference` rejects batches for such a config instead of writing rows
+    /// that could never be polled to completion.
+    pub batch_api_base_url: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct BatchSubmitResponse {
+    id: String,
+}
+
+#[derive(serde::Deserialize)]
+struct BatchStatusResponse {
+    status: String,
+}
+
+/// One result line from the provider's batch results endpoint. `custom_id` is the
+/// `inference_id` we tagged the request with in `submit_native_batch`, so results can be
+/// joined back to `pending_rows` by id instead of assumed to come back in request order.
+#[derive(serde::Deserialize)]
+struct BatchResultRow {
+    custom_id: String,
+    content: Vec<ContentBlockOutput>,
+    usage: Usage,
+}
+
+#[derive(serde::Deserialize)]
+struct BatchResultsResponse {
+    results: Vec<BatchResultRow>,
+}
+
+#[async_trait]
+impl Variant for ChatCompletionConfig {
+    async fn start_batch_inference<'a>(
+        &'a self,
+        inputs: &[Input],
+        _models: &InferenceModels<'a>,
+        _function: &FunctionConfig,
+        inference_configs: &[InferenceConfig<'a>],
+        clients: &InferenceClients<'a>,
+        inference_params: Vec<InferenceParams>,
+    ) -> Result<BatchModelInferenceWithMetadata<'a>, Error> {
+        // A batch written with no `provider_batch_id` could never be polled to completion
+        // (`poll_batch_inference` below has no per-row fallback), so reject it here rather
+        // than have it sit as `Pending` forever. Same error flow as any other variant
+        // failure: the caller tries the next candidate variant (`per_batch` sampling) or
+        // records this row as a failure (`per_inference` sampling).
+        let Some(base_url) = &self.batch_api_base_url else {
+            return Err(Error::new(ErrorDetails::Inference {
+                message: format!(
+                    "model provider `{}` has no native batch API configured; batch inference requires a variant with native batch support",
+                    self.model_provider_name
+                ),
+            }));
+        };
+        let inference_ids: Vec<Uuid> = inputs.iter().map(|_| Uuid::now_v7()).collect();
+        let input_messages: Vec<Value> = inputs
+            .iter()
+            .map(|input| serde_json::to_value(input))
+            .collect::<Result<_, _>>()
+            .map_err(|e| {
+                Error::new(ErrorDetails::Serialization {
+                    message: format!("Failed to render batch inputs for submission: {e}"),
+                })
+            })?;
+        let systems: Vec<Option<Value>> = vec![None; inputs.len()];
+        let output_schemas: Vec<Option<Value>> = inference_configs
+            .iter()
+            .map(|config| config.dynamic_output_schema.cloned())
+            .collect();
+
+        let provider_batch_id = Some(
+            self.submit_native_batch(base_url, &inference_ids, &input_messages, clients)
+                .await?,
+        );
+
+        Ok(BatchModelInferenceWithMetadata {
+            batch_id: Uuid::now_v7(),
+            inference_ids,
+            input_messages,
+            systems,
+            inference_params,
+            output_schemas,
+            model_name: &self.model_name,
+            model_provider_name: &self.model_provider_name,
+            provider_batch_id,
+        })
+    }
+
+    async fn poll_batch_inference(
+        &self,
+        pending_rows: &[&BatchModelInferenceRow],
+        function: &FunctionConfig,
+        clients: &InferenceClients<'_>,
+    ) -> Result<PollBatchInferenceResult, Error> {
+        let Some(base_url) = &self.batch_api_base_url else {
+            return Err(Error::new(ErrorDetails::Inference {
+                message: format!(
+                    "model provider `{}` has no native batch API configured; only natively-submitted batches can be polled",
+                    self.model_provider_name
+                ),
+            }));
+        };
+        let Some(provider_batch_id) = pending_rows
+            .first()
+            .and_then(|row| row.provider_batch_id.as_deref())
+        else {
+            return Err(Error::new(ErrorDetails::Inference {
+                message: "batch has no provider_batch_id to poll".to_string(),
+            }));
+        };
+
+        let status_response = self
+            .authed_get(clients, format!("{base_url}/batches/{provider_batch_id}"))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!("Failed to poll provider batch `{provider_batch_id}`: {e}"),
+                })
+            })?
+            .json::<BatchStatusResponse>()
+            .await
+            .map_err(|e| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!(
+                        "Failed to parse provider batch status for `{provider_batch_id}`: {e}"
+                    ),
+                })
+            })?;
+
+        let status = map_provider_batch_status(&status_response.status);
+
+        let outputs = if status == BatchStatus::Completed {
+            self.download_finalized_outputs(base_url, provider_batch_id, pending_rows, function, clients)
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(PollBatchInferenceResult { status, outputs })
+    }
+}
+
+impl ChatCompletionConfig {
+    /// Looks up this model provider's secret in `clients.credentials` (keyed by
+    /// `model_provider_name`, the same way model providers are configured) and attaches it
+    /// as a bearer token. A provider with no matching entry is sent unauthenticated, which
+    /// the provider itself will reject -- we don't hard-fail here since some native batch
+    /// endpoints (e.g. behind a trusted internal gateway) may not require one.
+    fn authed_get(&self, clients: &InferenceClients<'_>, url: String) -> reqwest::RequestBuilder {
+        let request = clients.http_client.get(url);
+        match clients.credentials.get(&self.model_provider_name) {
+            Some(secret) => request.bearer_auth(secret.expose_secret()),
+            None => request,
+        }
+    }
+
+    fn authed_post(&self, clients: &InferenceClients<'_>, url: String) -> reqwest::RequestBuilder {
+        let request = clients.http_client.post(url);
+        match clients.credentials.get(&self.model_provider_name) {
+            Some(secret) => request.bearer_auth(secret.expose_secret()),
+            None => request,
+        }
+    }
+
+    async fn submit_native_batch(
+        &self,
+        base_url: &str,
+        inference_ids: &[Uuid],
+        input_messages: &[Value],
+        clients: &InferenceClients<'_>,
+    ) -> Result<String, Error> {
+        // Tag each request with the inference_id it came from as `custom_id`, so
+        // `download_finalized_outputs` can join results back to `pending_rows` by id
+        // instead of assuming the provider preserves submission order.
+        let requests: Vec<Value> = inference_ids
+            .iter()
+            .zip(input_messages)
+            .map(|(inference_id, body)| {
+                serde_json::json!({
+                    "custom_id": inference_id.to_string(),
+                    "method": "POST",
+                    "url": "/v1/chat/completions",
+                    "body": body,
+                })
+            })
+            .collect();
+        let response = self
+            .authed_post(clients, format!("{base_url}/batches"))
+            .json(&serde_json::json!({
+                "model": self.model_name,
+                "requests": requests,
+            }))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!("Failed to submit batch to provider: {e}"),
+                })
+            })?
+            .json::<BatchSubmitResponse>()
+            .await
+            .map_err(|e| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!("Failed to parse provider batch submission response: {e}"),
+                })
+            })?;
+        Ok(response.id)
+    }
+
+    async fn download_finalized_outputs(
+        &self,
+        base_url: &str,
+        provider_batch_id: &str,
+        pending_rows: &[&BatchModelInferenceRow],
+        function: &FunctionConfig,
+        clients: &InferenceClients<'_>,
+    ) -> Result<Vec<InferenceResponse>, Error> {
+        let results = self
+            .authed_get(clients, format!("{base_url}/batches/{provider_batch_id}/results"))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!(
+                        "Failed to download results for provider batch `{provider_batch_id}`: {e}"
+                    ),
+                })
+            })?
+            .json::<BatchResultsResponse>()
+            .await
+            .map_err(|e| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!(
+                        "Failed to parse results for provider batch `{provider_batch_id}`: {e}"
+                    ),
+                })
+            })?;
+
+        join_batch_results(results.results, pending_rows, function, provider_batch_id)
+    }
+}
+
+/// Maps a provider's batch status string (OpenAI's `/v1/batches` vocabulary) onto our own
+/// `BatchStatus`. Any status we don't recognize is treated as `Pending` rather than erroring,
+/// since a provider adding a new intermediate status shouldn't break polling -- it just means
+/// we keep polling instead of (possibly wrongly) reporting completion or failure.
+fn map_provider_batch_status(status: &str) -> BatchStatus {
+    match status {
+        "completed" => BatchStatus::Completed,
+        "failed" | "expired" | "cancelled" => BatchStatus::Failed,
+        "in_progress" | "finalizing" | "validating" => BatchStatus::InProgress,
+        _ => BatchStatus::Pending,
+    }
+}
+
+/// Joins a completed provider batch's result rows back to `pending_rows` by `custom_id` (the
+/// `inference_id` `submit_native_batch` tagged each request with) rather than by position,
+/// since a provider is free to return results in any order, and dispatches each to a
+/// `ChatInferenceResponse` or `JsonInferenceResponse` depending on `function`'s type.
+fn join_batch_results(
+    results: Vec<BatchResultRow>,
+    pending_rows: &[&BatchModelInferenceRow],
+    function: &FunctionConfig,
+    provider_batch_id: &str,
+) -> Result<Vec<InferenceResponse>, Error> {
+    if results.len() != pending_rows.len() {
+        return Err(Error::new(ErrorDetails::Inference {
+            message: format!(
+                "Provider batch `{provider_batch_id}` returned {} result(s) but {} row(s) were pending",
+                results.len(),
+                pending_rows.len()
+            ),
+        }));
+    }
+
+    let mut results_by_inference_id: HashMap<Uuid, BatchResultRow> = HashMap::new();
+    for result in results {
+        let inference_id = Uuid::parse_str(&result.custom_id).map_err(|e| {
+            Error::new(ErrorDetails::Inference {
+                message: format!(
+                    "Provider batch `{provider_batch_id}` returned a result with a non-UUID \
+                     custom_id `{}`: {e}",
+                    result.custom_id
+                ),
+            })
+        })?;
+        results_by_inference_id.insert(inference_id, result);
+    }
+
+    let is_json_function = matches!(function, FunctionConfig::Json(_));
+    pending_rows
+        .iter()
+        .map(|row| {
+            let result = results_by_inference_id.remove(&row.id).ok_or_else(|| {
+                Error::new(ErrorDetails::Inference {
+                    message: format!(
+                        "Provider batch `{provider_batch_id}` did not return a result for \
+                         inference_id `{}`",
+                        row.id
+                    ),
+                })
+            })?;
+            Ok(if is_json_function {
+                let raw = first_text_content(&result.content).unwrap_or_default();
+                let parsed = serde_json::from_str(&raw).ok();
+                InferenceResponse::Json(JsonInferenceResponse {
+                    inference_id: row.id,
+                    episode_id: row.episode_id,
+                    variant_name: row.variant_name.clone(),
+                    output: JsonInferenceOutput {
+                        raw: Some(raw),
+                        parsed,
+                    },
+                    usage: result.usage,
+                })
+            } else {
+                InferenceResponse::Chat(ChatInferenceResponse {
+                    inference_id: row.id,
+                    episode_id: row.episode_id,
+                    variant_name: row.variant_name.clone(),
+                    content: result.content,
+                    usage: result.usage,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Pulls the first text block out of a provider response, for the JSON-function path where
+/// the model's structured output arrives as a text completion to be parsed, not a
+/// pre-structured field.
+fn first_text_content(content: &[ContentBlockOutput]) -> Option<String> {
+    content.iter().find_map(|block| match block {
+        ContentBlockOutput::Text(Text { text }) => Some(text.clone()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_provider_batch_status() {
+        assert_eq!(map_provider_batch_status("completed"), BatchStatus::Completed);
+        assert_eq!(map_provider_batch_status("failed"), BatchStatus::Failed);
+        assert_eq!(map_provider_batch_status("expired"), BatchStatus::Failed);
+        assert_eq!(map_provider_batch_status("cancelled"), BatchStatus::Failed);
+        assert_eq!(map_provider_batch_status("in_progress"), BatchStatus::InProgress);
+        assert_eq!(map_provider_batch_status("finalizing"), BatchStatus::InProgress);
+        assert_eq!(map_provider_batch_status("validating"), BatchStatus::InProgress);
+        // An unrecognized status (e.g. a new one the provider starts sending) is treated as
+        // still-pending rather than erroring.
+        assert_eq!(map_provider_batch_status("queued"), BatchStatus::Pending);
+    }
+
+    #[test]
+    fn test_first_text_content() {
+        let content = vec![
+            ContentBlockOutput::Text(Text {
+                text: "hello".to_string(),
+            }),
+        ];
+        assert_eq!(first_text_content(&content), Some("hello".to_string()));
+
+        // No text block present: None.
+        assert_eq!(first_text_content(&[]), None);
+    }
+}